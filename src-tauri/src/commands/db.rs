@@ -1,11 +1,15 @@
-use crate::db::{iterations, links, messages, sessions, settings};
+use crate::db::attempts::{self, RunAttempt};
+use crate::db::settings::FolderBudget;
+use crate::db::usage::{self, DateRange, UsageTotals};
+use crate::db::{env_vars, iterations, links, messages, sessions, settings};
 use crate::models::RalphIteration;
-use crate::providers::Provider;
+use crate::providers::{PermissionProfile, Provider};
 use crate::state::DB_CONNECTION;
+use crate::utils::env_file::EnvProfiles;
 use std::collections::HashMap;
 
 // Helper to get the database connection
-fn get_db() -> Result<&'static crate::db::DbConnection, String> {
+pub(crate) fn get_db() -> Result<&'static crate::db::DbConnection, String> {
     DB_CONNECTION
         .get()
         .ok_or_else(|| "Database not initialized".to_string())
@@ -236,3 +240,228 @@ pub fn db_set_folder_provider(folder_path: String, provider: Provider) -> Result
     let conn = get_db()?;
     settings::set_folder_provider(conn, &folder_path, provider)
 }
+
+#[tauri::command]
+pub fn db_get_folder_model(folder_path: String) -> Result<Option<String>, String> {
+    let conn = get_db()?;
+    settings::get_folder_model(conn, &folder_path)
+}
+
+#[tauri::command]
+pub fn db_set_folder_model(folder_path: String, model: Option<String>) -> Result<(), String> {
+    let conn = get_db()?;
+    let model = model.filter(|m| !m.trim().is_empty());
+    settings::set_folder_model(conn, &folder_path, model.as_deref())
+}
+
+#[tauri::command]
+pub fn db_get_folder_env_profile(folder_path: String) -> Result<Option<String>, String> {
+    let conn = get_db()?;
+    settings::get_folder_env_profile(conn, &folder_path)
+}
+
+#[tauri::command]
+pub fn db_set_folder_env_profile(
+    folder_path: String,
+    profile: Option<String>,
+) -> Result<(), String> {
+    let conn = get_db()?;
+    let profile = profile.filter(|p| !p.trim().is_empty());
+    settings::set_folder_env_profile(conn, &folder_path, profile.as_deref())
+}
+
+#[tauri::command]
+pub fn db_list_env_profiles(folder_path: String) -> Result<Vec<String>, String> {
+    let conn = get_db()?;
+    env_vars::list_env_profiles(conn, &folder_path)
+}
+
+#[tauri::command]
+pub fn db_get_env_vars(folder_path: String) -> Result<EnvProfiles, String> {
+    let conn = get_db()?;
+    env_vars::get_env_vars(conn, &folder_path)
+}
+
+#[tauri::command]
+pub fn db_set_env_var(
+    folder_path: String,
+    profile: String,
+    key: String,
+    value: String,
+) -> Result<(), String> {
+    let conn = get_db()?;
+    env_vars::set_env_var(conn, &folder_path, &profile, &key, &value)
+}
+
+#[tauri::command]
+pub fn db_delete_env_var(folder_path: String, profile: String, key: String) -> Result<(), String> {
+    let conn = get_db()?;
+    env_vars::delete_env_var(conn, &folder_path, &profile, &key)
+}
+
+#[tauri::command]
+pub fn db_get_folder_permission_profile(
+    folder_path: String,
+) -> Result<Option<PermissionProfile>, String> {
+    let conn = get_db()?;
+    settings::get_folder_permission_profile(conn, &folder_path)
+}
+
+#[tauri::command]
+pub fn db_set_folder_permission_profile(
+    folder_path: String,
+    profile: Option<PermissionProfile>,
+) -> Result<(), String> {
+    let conn = get_db()?;
+    settings::set_folder_permission_profile(conn, &folder_path, profile.as_ref())
+}
+
+#[tauri::command]
+pub fn db_get_prd_permission_profile(
+    folder_path: String,
+    prd_name: String,
+) -> Result<Option<PermissionProfile>, String> {
+    let conn = get_db()?;
+    settings::get_prd_permission_profile(conn, &folder_path, &prd_name)
+}
+
+#[tauri::command]
+pub fn db_set_prd_permission_profile(
+    folder_path: String,
+    prd_name: String,
+    profile: Option<PermissionProfile>,
+) -> Result<(), String> {
+    let conn = get_db()?;
+    settings::set_prd_permission_profile(conn, &folder_path, &prd_name, profile.as_ref())
+}
+
+#[tauri::command]
+pub fn db_get_folder_max_concurrent_runs(folder_path: String) -> Result<Option<usize>, String> {
+    let conn = get_db()?;
+    settings::get_folder_max_concurrent_runs(conn, &folder_path)
+}
+
+#[tauri::command]
+pub fn db_set_folder_max_concurrent_runs(
+    folder_path: String,
+    limit: Option<usize>,
+) -> Result<(), String> {
+    let conn = get_db()?;
+    settings::set_folder_max_concurrent_runs(conn, &folder_path, limit.filter(|n| *n > 0))
+}
+
+#[tauri::command]
+pub fn db_get_folder_fallback_providers(folder_path: String) -> Result<Vec<String>, String> {
+    let conn = get_db()?;
+    settings::get_folder_fallback_providers(conn, &folder_path)
+}
+
+#[tauri::command]
+pub fn db_set_folder_fallback_providers(
+    folder_path: String,
+    providers: Vec<Provider>,
+) -> Result<(), String> {
+    let conn = get_db()?;
+    settings::set_folder_fallback_providers(conn, &folder_path, &providers)
+}
+
+#[tauri::command]
+pub fn db_get_folder_budget(folder_path: String) -> Result<FolderBudget, String> {
+    let conn = get_db()?;
+    settings::get_folder_budget(conn, &folder_path)
+}
+
+#[tauri::command]
+pub fn db_set_folder_budget(folder_path: String, budget: FolderBudget) -> Result<(), String> {
+    let conn = get_db()?;
+    settings::set_folder_budget(conn, &folder_path, &budget)
+}
+
+// ============================================================================
+// App Settings Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn db_get_app_setting(key: String) -> Result<Option<String>, String> {
+    let conn = get_db()?;
+    settings::get_app_setting(conn, &key)
+}
+
+#[tauri::command]
+pub fn db_set_app_setting(key: String, value: String) -> Result<(), String> {
+    let conn = get_db()?;
+    settings::set_app_setting(conn, &key, &value)
+}
+
+// ============================================================================
+// Run Attempt Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn db_get_process_attempts(process_id: String) -> Result<Vec<RunAttempt>, String> {
+    let conn = get_db()?;
+    attempts::get_process_attempts(conn, &process_id)
+}
+
+#[tauri::command]
+pub fn db_get_iteration_attempts(
+    folder_path: String,
+    prd_name: String,
+    iteration_number: i32,
+) -> Result<Vec<RunAttempt>, String> {
+    let conn = get_db()?;
+    attempts::get_iteration_attempts(conn, &folder_path, &prd_name, iteration_number)
+}
+
+// ============================================================================
+// Usage Commands
+// ============================================================================
+// Date bounds are optional: `from` is inclusive, `to` exclusive, given as
+// RFC 3339 timestamps in any offset or YYYY-MM-DD dates (UTC).
+
+#[tauri::command]
+pub fn db_get_session_usage(
+    session_id: String,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<UsageTotals, String> {
+    let conn = get_db()?;
+    let range = DateRange::parse(from.as_deref(), to.as_deref())?;
+    usage::get_session_usage(conn, &session_id, &range)
+}
+
+#[tauri::command]
+pub fn db_get_iteration_usage(
+    folder_path: String,
+    prd_name: String,
+    iteration_number: i32,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<UsageTotals, String> {
+    let conn = get_db()?;
+    let range = DateRange::parse(from.as_deref(), to.as_deref())?;
+    usage::get_iteration_usage(conn, &folder_path, &prd_name, iteration_number, &range)
+}
+
+#[tauri::command]
+pub fn db_get_prd_usage(
+    folder_path: String,
+    prd_name: String,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<UsageTotals, String> {
+    let conn = get_db()?;
+    let range = DateRange::parse(from.as_deref(), to.as_deref())?;
+    usage::get_prd_usage(conn, &folder_path, &prd_name, &range)
+}
+
+#[tauri::command]
+pub fn db_get_folder_usage(
+    folder_path: String,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<UsageTotals, String> {
+    let conn = get_db()?;
+    let range = DateRange::parse(from.as_deref(), to.as_deref())?;
+    usage::get_folder_usage(conn, &folder_path, &range)
+}
@@ -1,23 +1,154 @@
-use crate::providers::Provider;
-use crate::state::AI_PROCESSES;
-use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+use crate::commands::db::get_db;
+use crate::db::attempts::{self, RunAttempt};
+use crate::db::messages::MessageBatcher;
+use crate::db::usage::{self, UsageRecord};
+use crate::db::{budget, env_vars, iterations, links, sessions, settings};
+use crate::models::{
+    ProcessInfo, ProcessOutput, QueuedRunInfo, RalphIterationRef, RunOptions, RunningProcessInfo,
+};
+use crate::providers::stream::{self, StreamEvent};
+use crate::providers::usage::UsageTracker;
+use crate::providers::{health, registry, LaunchOptions, Provider, RunnerMode, Version};
+use crate::state::{
+    AIProcess, ProcessActivity, QueuedRun, AI_PROCESSES, PROVIDER_HEALTH, RUN_QUEUE,
+};
+use crate::utils::env_file::{EnvVars, DEFAULT_ENV_PROFILE};
+use crate::utils::line_decoder::LineDecoder;
+use crate::utils::output_buffer::{BufferedSince, OutputBuffer};
+#[cfg(unix)]
+use crate::utils::process_group;
+use crate::utils::process_group::StopGrace;
+#[cfg(unix)]
+use crate::utils::prompt_pipe::PromptPipe;
+use crate::utils::retry;
+use crate::utils::run_log::{self, RunLog, RunLogStart};
+use crate::utils::run_queue::{Queued, RunPriority};
+use crate::utils::shell_env;
+use chrono::Utc;
+use portable_pty::{native_pty_system, Child, ChildKiller, CommandBuilder, PtySize};
 use serde::Serialize;
-use std::io::Read;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use tauri::{AppHandle, Emitter};
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter, Runtime};
 use uuid::Uuid;
 
+// Output events carry their offset in the process's backlog (see `get_process_output`)
 #[derive(Clone, Serialize)]
 struct AIOutput {
     process_id: String,
+    offset: u64,
     data: String,
 }
 
+#[derive(Clone, Serialize)]
+struct AIStderr {
+    process_id: String,
+    offset: u64,
+    data: String,
+}
+
+#[derive(Clone, Serialize)]
+struct AIEvent {
+    process_id: String,
+    offset: u64,
+    event: StreamEvent,
+}
+
+#[derive(Clone, Serialize)]
+struct AISession {
+    process_id: String,
+    session_id: String,
+}
+
+/// Why a run ended
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ExitReason {
+    /// The provider exited on its own
+    Exited,
+    /// Stopped by the user via `stop_provider`
+    Stopped,
+    /// Terminated by the watchdog (see `AITimeout`)
+    TimedOut,
+    /// Removed from the run queue before it started
+    Cancelled,
+    /// Still failing transiently after every retry (see `AIRetry`)
+    Failed,
+}
+
+impl ExitReason {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Exited => "exited",
+            Self::Stopped => "stopped",
+            Self::TimedOut => "timed_out",
+            Self::Cancelled => "cancelled",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// Which limit a run exceeded
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum TimeoutKind {
+    MaxDuration,
+    Idle,
+}
+
+#[derive(Clone, Serialize)]
+struct AITimeout {
+    process_id: String,
+    kind: TimeoutKind,
+    limit_secs: u64,
+    elapsed_secs: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct AIQueued {
+    process_id: String,
+    folder_path: String,
+    priority: RunPriority,
+    position: usize,
+}
+
+#[derive(Clone, Serialize)]
+struct AIStarted {
+    process_id: String,
+    queued_ms: u64,
+}
+
+/// A process left `list_running_processes`; `reason` and `code` are unset
+/// when the provider couldn't be run
+#[derive(Clone, Serialize)]
+struct ProcessEnded {
+    process_id: String,
+    folder_path: String,
+    reason: Option<ExitReason>,
+    code: Option<i32>,
+}
+
+/// A transient failure; the run is retried as `attempt` after `delay_secs`
+#[derive(Clone, Serialize)]
+struct AIRetry {
+    process_id: String,
+    attempt: u32,
+    max_retries: u32,
+    delay_secs: u64,
+    error: String,
+}
+
 #[derive(Clone, Serialize)]
 struct AIExit {
     process_id: String,
     code: i32,
+    reason: ExitReason,
 }
 
 #[derive(Clone, Serialize)]
@@ -33,7 +164,57 @@ pub async fn run_provider(
     message: String,
     folder_path: String,
     session_id: Option<String>,
+    options: Option<RunOptions>,
 ) -> Result<String, String> {
+    let options = options.unwrap_or_default();
+
+    // Refuse to start a Ralph iteration once the folder's budget is spent
+    check_budget(&app, &folder_path, options.ralph_iteration.as_ref())?;
+
+    // A model chosen for this run wins over the folder's model, which only
+    // applies to the folder's provider (not, say, a Ralph fallback)
+    let model = match options.model.filter(|m| !m.trim().is_empty()) {
+        Some(model) => Some(model),
+        None if settings::get_folder_provider(get_db()?, &folder_path)
+            .is_ok_and(|folder_provider| folder_provider == provider) =>
+        {
+            settings::get_folder_model(get_db()?, &folder_path)?
+        }
+        None => None,
+    };
+
+    // Refuse rather than run on whatever model the provider defaults to
+    if model.is_some() && !provider.supports_model() {
+        return Err(format!(
+            "{} can't run a chosen model. Clear the model or choose another provider.",
+            provider.display_name()
+        ));
+    }
+
+    // Refuse rather than run with broader permissions than chosen
+    let permission_profile = settings::resolve_permission_profile(
+        get_db()?,
+        &folder_path,
+        options
+            .ralph_iteration
+            .as_ref()
+            .map(|it| it.prd_name.as_str()),
+    )?;
+    if !provider.supports_permissions(&permission_profile) {
+        return Err(format!(
+            "{} can't enforce the '{}' permission profile. Choose another profile or provider.",
+            provider.display_name(),
+            permission_profile.name()
+        ));
+    }
+
+    // Variables for the folder's env profile, or the one chosen for this run
+    let env_profile = match options.env_profile.filter(|p| !p.trim().is_empty()) {
+        Some(profile) => Some(profile),
+        None => settings::get_folder_env_profile(get_db()?, &folder_path)?,
+    };
+    let env = env_vars::resolve_env(get_db()?, &folder_path, env_profile.as_deref())?;
+
     // Generate unique process ID
     let process_id = Uuid::new_v4().to_string();
     let process_id_clone = process_id.clone();
@@ -42,37 +223,159 @@ pub async fn run_provider(
     let stop_flag = Arc::new(AtomicBool::new(false));
     let stop_flag_clone = stop_flag.clone();
 
-    // Store the stop flag
-    {
-        let mut processes = AI_PROCESSES.lock().map_err(|e| e.to_string())?;
-        processes.insert(process_id.clone(), stop_flag);
-    }
+    // Tracked once the run leaves the queue, so views can find it (and its session) again
+    let output = Arc::new(Mutex::new(OutputBuffer::new(
+        OUTPUT_BACKLOG_ENTRIES,
+        OUTPUT_BACKLOG_BYTES,
+    )));
+    let activity = Arc::new(Mutex::new(ProcessActivity::default()));
+    let process = AIProcess {
+        stop_flag,
+        output: output.clone(),
+        activity: activity.clone(),
+        info: ProcessInfo {
+            process_id: process_id.clone(),
+            provider: provider.id().to_string(),
+            folder_path: folder_path.clone(),
+            session_type: options.session_type.unwrap_or_else(|| "plan".to_string()),
+            session_id: session_id.clone(),
+            model: model.clone(),
+            permission_profile: permission_profile.clone(),
+            ralph_iteration: options.ralph_iteration.clone(),
+        },
+    };
+    let priority = if options.ralph_iteration.is_some() {
+        RunPriority::Ralph
+    } else {
+        RunPriority::Interactive
+    };
+
+    // Limits from the request win over the global defaults
+    let setting = |key: &str| {
+        get_db()
+            .and_then(|conn| settings::get_app_setting_u64(conn, key))
+            .unwrap_or(None)
+    };
+    let max_duration = options
+        .max_duration
+        .or_else(|| setting("max_duration_secs"))
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs);
+    let idle_timeout = options
+        .idle_timeout
+        .or_else(|| setting("idle_timeout_secs"))
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs);
 
     let app_clone = app.clone();
+    let queued_folder_path = folder_path.clone();
+
+    let start = move || {
+        let _slot = RunSlot {
+            app: app_clone.clone(),
+            process_id: process_id_clone.clone(),
+            folder_path: folder_path.clone(),
+        };
+
+        // Record where the iteration actually runs, which may be a fallback
+        if let Some(iteration) = &options.ralph_iteration {
+            if let Err(e) = get_db().and_then(|conn| {
+                iterations::update_ralph_iteration_provider(
+                    conn,
+                    &folder_path,
+                    &iteration.prd_name,
+                    iteration.iteration_number,
+                    provider.id(),
+                )
+            }) {
+                eprintln!("Failed to record iteration provider: {}", e);
+            }
+        }
+        let provider_id = provider.id().to_string();
 
-    std::thread::spawn(move || {
-        let result = run_provider_process(
-            &app_clone,
+        // Make room for this run's log under the retention settings
+        prune_run_logs();
+
+        let run = ProviderRun {
             provider,
-            &message,
-            &folder_path,
-            session_id.as_deref(),
-            &process_id_clone,
-            stop_flag_clone,
-        );
+            message,
+            folder_path: folder_path.clone(),
+            session_id,
+            user_message: options.user_message,
+            env,
+            env_profile,
+            // Whether the prompt goes to stdin is decided per attempt
+            launch: LaunchOptions {
+                model,
+                permissions: permission_profile,
+                ..Default::default()
+            },
+            process_id: process_id_clone.clone(),
+            attempt: 1,
+            stop_flag: stop_flag_clone,
+            output,
+            activity,
+            max_duration,
+            idle_timeout,
+        };
+
+        // Spending may have gone on while this run waited in the queue
+        let result = check_budget(&app_clone, &folder_path, options.ralph_iteration.as_ref())
+            .and_then(|_| run_with_retries(&app_clone, run));
 
         // Remove process from tracking
         if let Ok(mut processes) = AI_PROCESSES.lock() {
             processes.remove(&process_id_clone);
         }
+        let (reason, code) = match &result {
+            Ok(outcome) => (Some(outcome.reason), Some(outcome.code)),
+            Err(_) => (None, None),
+        };
+        let _ = app_clone.emit(
+            "process-ended",
+            ProcessEnded {
+                process_id: process_id_clone.clone(),
+                folder_path: folder_path.clone(),
+                reason,
+                code,
+            },
+        );
 
         match result {
-            Ok(code) => {
+            Ok(RunOutcome {
+                code,
+                reason,
+                transient_error,
+                auth_error,
+            }) => {
+                update_provider_health(&provider_id, code, reason, transient_error, auth_error);
+
+                // Record timeouts and failures on the iteration even if no view is listening
+                let status = match reason {
+                    ExitReason::TimedOut => Some("timed_out"),
+                    ExitReason::Failed => Some("failed"),
+                    _ => None,
+                };
+                if let (Some(status), Some(iteration)) = (status, &options.ralph_iteration) {
+                    if let Err(e) = get_db().and_then(|conn| {
+                        iterations::update_ralph_iteration_status(
+                            conn,
+                            &folder_path,
+                            &iteration.prd_name,
+                            iteration.iteration_number,
+                            status,
+                        )
+                    }) {
+                        eprintln!("Failed to mark iteration {}: {}", status, e);
+                    }
+                }
+
                 let _ = app_clone.emit(
                     "ai-exit",
                     AIExit {
-                        process_id: process_id_clone,
+                        process_id: process_id_clone.clone(),
                         code,
+                        reason,
                     },
                 );
             }
@@ -80,42 +383,488 @@ pub async fn run_provider(
                 let _ = app_clone.emit(
                     "ai-error",
                     AIError {
-                        process_id: process_id_clone,
+                        process_id: process_id_clone.clone(),
                         error: e,
                     },
                 );
             }
         }
-    });
+    };
+
+    let position = RUN_QUEUE.lock().map_err(|e| e.to_string())?.push(
+        process_id.clone(),
+        queued_folder_path.clone(),
+        priority,
+        QueuedRun {
+            process,
+            queued_at: Instant::now(),
+            start: Box::new(start),
+        },
+    );
+    let _ = app.emit(
+        "ai-queued",
+        AIQueued {
+            process_id: process_id.clone(),
+            folder_path: queued_folder_path,
+            priority,
+            position,
+        },
+    );
+    dispatch_queued_runs(&app);
 
     Ok(process_id)
 }
 
+/// How much recent output each running process keeps for views that
+/// reconnect mid-run
+const OUTPUT_BACKLOG_ENTRIES: usize = 20_000;
+const OUTPUT_BACKLOG_BYTES: usize = 16 * 1024 * 1024;
+
+/// Concurrent provider runs across all folders, unless the
+/// `max_concurrent_runs` app setting says otherwise
+const DEFAULT_MAX_CONCURRENT_RUNS: usize = 3;
+
+/// A started run's hold on its queue slot. Dropping it, however the run's
+/// thread ends, stops tracking the run and hands the slot to the next one.
+struct RunSlot {
+    app: AppHandle,
+    process_id: String,
+    folder_path: String,
+}
+
+impl Drop for RunSlot {
+    fn drop(&mut self) {
+        if let Ok(mut processes) = AI_PROCESSES.lock() {
+            processes.remove(&self.process_id);
+        }
+
+        // A panicking run never reported how it ended
+        if std::thread::panicking() {
+            let _ = self.app.emit(
+                "process-ended",
+                ProcessEnded {
+                    process_id: self.process_id.clone(),
+                    folder_path: self.folder_path.clone(),
+                    reason: None,
+                    code: None,
+                },
+            );
+            let _ = self.app.emit(
+                "ai-error",
+                AIError {
+                    process_id: self.process_id.clone(),
+                    error: "The run stopped unexpectedly".to_string(),
+                },
+            );
+        }
+
+        if let Ok(mut queue) = RUN_QUEUE.lock() {
+            queue.finish(&self.process_id);
+        }
+        dispatch_queued_runs(&self.app);
+    }
+}
+
+/// Start every queued run the global and per-folder limits allow
+fn dispatch_queued_runs(app: &AppHandle) {
+    let conn = get_db().ok();
+    let global_limit = conn
+        .and_then(|conn| settings::get_app_setting_u64(conn, "max_concurrent_runs").ok())
+        .flatten()
+        .filter(|limit| *limit > 0)
+        .map_or(DEFAULT_MAX_CONCURRENT_RUNS, |limit| limit as usize);
+    let folder_limit = |folder: &str| {
+        conn.and_then(|conn| settings::get_folder_max_concurrent_runs(conn, folder).ok())
+            .flatten()
+    };
+
+    let started = {
+        let Ok(mut queue) = RUN_QUEUE.lock() else {
+            return;
+        };
+        let Ok(mut processes) = AI_PROCESSES.lock() else {
+            return;
+        };
+
+        // Track runs before the queue is released, so stop_provider always finds them
+        queue
+            .take_ready(global_limit, folder_limit)
+            .into_iter()
+            .map(|Queued { id, job, .. }| {
+                if let Ok(mut activity) = job.process.activity.lock() {
+                    activity.started_at = Utc::now();
+                }
+                processes.insert(id.clone(), job.process);
+                (id, job.queued_at, job.start)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    for (process_id, queued_at, start) in started {
+        let _ = app.emit(
+            "ai-started",
+            AIStarted {
+                process_id,
+                queued_ms: queued_at.elapsed().as_millis() as u64,
+            },
+        );
+        std::thread::spawn(start);
+    }
+}
+
+/// Report a run that was taken out of the queue before it started
+fn cancel_run(app: &AppHandle, process_id: &str, run: QueuedRun) {
+    let info = run.process.info;
+    if let Some(iteration) = &info.ralph_iteration {
+        if let Err(e) = get_db().and_then(|conn| {
+            iterations::update_ralph_iteration_status(
+                conn,
+                &info.folder_path,
+                &iteration.prd_name,
+                iteration.iteration_number,
+                "stopped",
+            )
+        }) {
+            eprintln!("Failed to mark iteration stopped: {}", e);
+        }
+    }
+
+    let _ = app.emit(
+        "ai-exit",
+        AIExit {
+            process_id: process_id.to_string(),
+            code: -1,
+            reason: ExitReason::Cancelled,
+        },
+    );
+}
+
+/// Queued runs in the order they will start
 #[tauri::command]
-pub fn stop_provider(process_id: Option<String>) -> Result<(), String> {
+pub fn list_queued_runs() -> Result<Vec<QueuedRunInfo>, String> {
+    let queue = RUN_QUEUE.lock().map_err(|e| e.to_string())?;
+    Ok(queue
+        .pending()
+        .enumerate()
+        .map(|(position, queued)| QueuedRunInfo {
+            info: queued.job.process.info.clone(),
+            priority: queued.priority,
+            position,
+            queued_secs: queued.job.queued_at.elapsed().as_secs(),
+        })
+        .collect())
+}
+
+/// Remove a run from the queue before it starts
+#[tauri::command]
+pub fn cancel_queued_run(app: AppHandle, process_id: String) -> Result<(), String> {
+    let run = RUN_QUEUE
+        .lock()
+        .map_err(|e| e.to_string())?
+        .cancel(&process_id)
+        .ok_or_else(|| format!("Run {} is not queued", process_id))?;
+    cancel_run(&app, &process_id, run.job);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_provider(app: AppHandle, process_id: Option<String>) -> Result<(), String> {
+    // Runs that haven't started yet are just taken out of the queue
+    let cancelled = {
+        let mut queue = RUN_QUEUE.lock().map_err(|e| e.to_string())?;
+        match &process_id {
+            Some(pid) => queue.cancel(pid).into_iter().collect(),
+            None => queue.cancel_all(),
+        }
+    };
+    for run in cancelled {
+        cancel_run(&app, &run.id, run.job);
+    }
+
     let processes = AI_PROCESSES.lock().map_err(|e| e.to_string())?;
 
     if let Some(pid) = process_id {
         // Stop specific process
-        if let Some(stop_flag) = processes.get(&pid) {
-            stop_flag.store(true, Ordering::SeqCst);
+        if let Some(process) = processes.get(&pid) {
+            process.stop_flag.store(true, Ordering::SeqCst);
         }
     } else {
         // Stop all processes
-        for stop_flag in processes.values() {
-            stop_flag.store(true, Ordering::SeqCst);
+        for process in processes.values() {
+            process.stop_flag.store(true, Ordering::SeqCst);
         }
     }
 
     Ok(())
 }
 
+/// Get the tracked info (including the captured session ID) for a running process
+#[tauri::command]
+pub fn get_process_info(process_id: String) -> Result<Option<ProcessInfo>, String> {
+    let processes = AI_PROCESSES.lock().map_err(|e| e.to_string())?;
+    Ok(processes.get(&process_id).map(|p| p.info.clone()))
+}
+
+/// Every run that has left the queue and not yet ended, across all folders
+#[tauri::command]
+pub fn list_running_processes() -> Result<Vec<RunningProcessInfo>, String> {
+    // Snapshot first, so the database isn't queried under the process lock
+    let processes: Vec<_> = {
+        let processes = AI_PROCESSES.lock().map_err(|e| e.to_string())?;
+        processes
+            .values()
+            .map(|p| (p.info.clone(), p.activity.clone()))
+            .collect()
+    };
+
+    let mut running: Vec<_> = processes
+        .into_iter()
+        .map(|(info, activity)| describe_process(info, &activity))
+        .collect();
+    running.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    Ok(running)
+}
+
+/// A running process with what its session is linked to and what it has done so far
+fn describe_process(info: ProcessInfo, activity: &Mutex<ProcessActivity>) -> RunningProcessInfo {
+    let session_links = match (get_db(), &info.session_id) {
+        (Ok(conn), Some(session_id)) => {
+            links::get_session_links(conn, &info.folder_path, session_id).unwrap_or_default()
+        }
+        _ => Vec::new(),
+    };
+    let linked = |link_type: &str| {
+        session_links
+            .iter()
+            .find(|link| link.link_type == link_type)
+            .map(|link| link.file_name.clone())
+    };
+    let activity = activity
+        .lock()
+        .map(|activity| activity.clone())
+        .unwrap_or_default();
+
+    RunningProcessInfo {
+        plan: linked("plan"),
+        prd: info
+            .ralph_iteration
+            .as_ref()
+            .map(|iteration| iteration.prd_name.clone())
+            .or_else(|| linked("ralph_prd")),
+        pid: activity.pid,
+        started_at: activity.started_at.to_rfc3339(),
+        bytes_received: activity.bytes_received,
+        last_output_at: activity.last_output_at.map(|at| at.to_rfc3339()),
+        info,
+    }
+}
+
+/// Catch up on a running process's output: everything still held from
+/// `since_offset` on. Subscribe to the live events first, then skip any with an
+/// offset below the returned `next_offset`, so nothing is missed or repeated.
+#[tauri::command]
+pub fn get_process_output(
+    process_id: String,
+    since_offset: u64,
+) -> Result<Option<BufferedSince<ProcessOutput>>, String> {
+    let output = {
+        let processes = AI_PROCESSES.lock().map_err(|e| e.to_string())?;
+        match processes.get(&process_id) {
+            Some(process) => process.output.clone(),
+            None => return Ok(None),
+        }
+    };
+    let output = output.lock().map_err(|e| e.to_string())?;
+    Ok(Some(output.since(since_offset)))
+}
+
+/// A run's raw log
+#[derive(Serialize)]
+pub struct RunLogContents {
+    process_id: String,
+    #[serde(flatten)]
+    contents: run_log::LogContents,
+}
+
+/// Read a run's raw log, or only its last `tail_bytes`. The run is the given
+/// process, else the latest run in `session_id`, else the latest attempt at a
+/// Ralph iteration.
+#[tauri::command]
+pub fn get_run_log(
+    folder_path: String,
+    process_id: Option<String>,
+    session_id: Option<String>,
+    prd_name: Option<String>,
+    iteration_number: Option<i32>,
+    tail_bytes: Option<u64>,
+) -> Result<RunLogContents, String> {
+    let latest = |attempts: Vec<RunAttempt>| attempts.last().map(|a| a.process_id.clone());
+    let process_id = match (process_id, session_id, prd_name, iteration_number) {
+        (Some(process_id), ..) => Some(process_id),
+        (None, Some(session_id), ..) => {
+            latest(attempts::get_session_attempts(get_db()?, &session_id)?)
+        }
+        (None, None, Some(prd_name), Some(iteration_number)) => latest(
+            attempts::get_iteration_attempts(get_db()?, &folder_path, &prd_name, iteration_number)?,
+        ),
+        _ => return Err("Choose a process, session or iteration".to_string()),
+    }
+    .ok_or("No runs recorded yet")?;
+
+    let path = run_log::log_path(&folder_path, &process_id)?;
+    if !path.exists() {
+        return Err(format!("No log for run {}", process_id));
+    }
+    Ok(RunLogContents {
+        contents: run_log::read_log(&path, tail_bytes)?,
+        process_id,
+    })
+}
+
+/// Apply the log retention settings to every folder's raw run logs
+fn prune_run_logs() {
+    let retention = get_db()
+        .and_then(settings::get_log_retention)
+        .unwrap_or_default();
+    if let Ok(root) = run_log::logs_root() {
+        run_log::prune_logs(&root, &retention, SystemTime::now());
+    }
+}
+
+/// How long a provider that ended a run logged out or rate-limited is skipped
+/// by Ralph fallbacks, unless the `provider_cooldown_secs` app setting says otherwise
+const DEFAULT_PROVIDER_COOLDOWN_SECS: u64 = 15 * 60;
+
+/// Track whether a provider's run says it can take the next Ralph iteration
+fn update_provider_health(
+    provider_id: &str,
+    code: i32,
+    reason: ExitReason,
+    transient_error: Option<String>,
+    auth_error: Option<String>,
+) {
+    let Ok(mut health) = PROVIDER_HEALTH.lock() else {
+        return;
+    };
+    match (reason, transient_error, auth_error) {
+        (ExitReason::Failed, Some(error), _) | (_, _, Some(error)) => {
+            health.mark_unhealthy(provider_id, error)
+        }
+        (ExitReason::Exited, None, None) if code == 0 => health.mark_healthy(provider_id),
+        _ => {}
+    }
+}
+
+/// The provider a Ralph iteration should run on
 #[derive(Clone, Serialize)]
+pub struct ProviderSelection {
+    pub provider: Provider,
+    /// Why the preferred provider was skipped, when `provider` is a fallback
+    pub fallback_reason: Option<String>,
+}
+
+/// Pick the first healthy provider from the preferred one and the folder's
+/// fallbacks. A provider is skipped while it is cooling down from a run that
+/// ended logged out or rate-limited, when `check_provider_available` fails, or
+/// when it can't enforce the PRD's permission profile. With none healthy, the
+/// preferred provider is returned so its own error is reported.
+#[tauri::command]
+pub fn select_ralph_provider(
+    folder_path: String,
+    prd_name: String,
+    provider: Provider,
+) -> Result<ProviderSelection, String> {
+    let conn = get_db()?;
+    let fallbacks = settings::get_folder_fallback_providers(conn, &folder_path)?;
+    let preferred = ProviderSelection {
+        provider: provider.clone(),
+        fallback_reason: None,
+    };
+    if fallbacks.is_empty() {
+        return Ok(preferred);
+    }
+
+    let cooldown = Duration::from_secs(
+        settings::get_app_setting_u64(conn, "provider_cooldown_secs")?
+            .unwrap_or(DEFAULT_PROVIDER_COOLDOWN_SECS),
+    );
+    let permission_profile =
+        settings::resolve_permission_profile(conn, &folder_path, Some(&prd_name))?;
+
+    let mut fallback_reason = None;
+    for id in health::fallback_chain(provider.id(), &fallbacks) {
+        // Providers removed since the chain was saved are skipped
+        let Ok(candidate) = Provider::from_id(&id) else {
+            continue;
+        };
+
+        let cooling_down = PROVIDER_HEALTH.lock().ok().and_then(|health| {
+            health
+                .unhealthy_reason(&id, cooldown)
+                .map(|reason| format!("{} failed recently: {}", candidate.display_name(), reason))
+        });
+        let unhealthy = match cooling_down {
+            Some(reason) => Some(reason),
+            None if !candidate.supports_permissions(&permission_profile) => Some(format!(
+                "{} can't enforce the '{}' permission profile",
+                candidate.display_name(),
+                permission_profile.name()
+            )),
+            None => {
+                let status = check_provider_available(candidate.clone());
+                (!status.available).then(|| {
+                    status
+                        .error
+                        .unwrap_or_else(|| format!("{} is not available", candidate.display_name()))
+                })
+            }
+        };
+
+        match unhealthy {
+            Some(reason) => {
+                eprintln!("Skipping {} for {}: {}", id, folder_path, reason);
+                fallback_reason.get_or_insert(reason);
+            }
+            None if candidate == provider => return Ok(preferred),
+            None => {
+                return Ok(ProviderSelection {
+                    provider: candidate,
+                    fallback_reason,
+                })
+            }
+        }
+    }
+
+    Ok(preferred)
+}
+
+#[derive(Clone, Default, Serialize)]
 pub struct ProviderStatus {
     pub available: bool,
     pub error: Option<String>,
-    pub error_type: Option<String>, // "not_installed", "not_logged_in", "unknown"
+    pub error_type: Option<String>, // "not_installed", "not_logged_in", "unsupported_version", "unknown"
     pub auth_instructions: Option<String>, // Instructions for authenticating
+    pub version: Option<String>,    // Parsed from `--version` output
+    pub binary_path: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ProviderInfo {
+    pub id: String,
+    pub display_name: String,
+}
+
+#[tauri::command]
+pub fn list_providers() -> Vec<ProviderInfo> {
+    registry::ids()
+        .into_iter()
+        .filter_map(|id| Provider::from_id(&id).ok())
+        .filter(|provider| !provider.hidden())
+        .map(|provider| ProviderInfo {
+            id: provider.id().to_string(),
+            display_name: provider.display_name().to_string(),
+        })
+        .collect()
 }
 
 #[tauri::command]
@@ -129,31 +878,49 @@ pub fn check_provider_available(provider: Provider) -> ProviderStatus {
                 error: Some(provider.not_installed_message()),
                 error_type: Some("not_installed".to_string()),
                 auth_instructions: None,
+                ..Default::default()
             };
         }
     };
+    let found = |mut status: ProviderStatus, version: Option<Version>| {
+        status.version = version.map(|v| v.to_string());
+        status.binary_path = Some(binary_path.display().to_string());
+        status
+    };
+
+    // Providers without a version command skip straight to the auth check
+    let version_args = provider.version_args();
+    if version_args.is_empty() {
+        return found(check_provider_authenticated(&provider), None);
+    }
 
-    // Verify it runs
+    // Verify it runs, with the PATH runs get (npm-installed CLIs need node)
     match std::process::Command::new(&binary_path)
-        .arg("--version")
+        .args(&version_args)
+        .env_clear()
+        .envs(shell_env::login_env())
         .output()
     {
+        // Binary works, now check it's new enough and authenticated
         Ok(output) if output.status.success() => {
-            // Binary works, now check authentication
-            match provider.check_authenticated() {
-                Ok(()) => ProviderStatus {
-                    available: true,
-                    error: None,
-                    error_type: None,
-                    auth_instructions: None,
-                },
-                Err(auth_error) => ProviderStatus {
+            let version = Version::parse(&String::from_utf8_lossy(&output.stdout));
+            let status = match (version, provider.min_version()) {
+                (Some(version), Some(min)) if version < min => ProviderStatus {
                     available: false,
-                    error: Some(auth_error),
-                    error_type: Some("not_logged_in".to_string()),
-                    auth_instructions: Some(provider.auth_instructions().to_string()),
+                    error: Some(format!(
+                        "{} {} at {} is too old; Trellico needs {} or newer. Update it, or point Trellico at a newer install in settings.",
+                        provider.display_name(),
+                        version,
+                        binary_path.display(),
+                        min
+                    )),
+                    error_type: Some("unsupported_version".to_string()),
+                    auth_instructions: None,
+                    ..Default::default()
                 },
-            }
+                _ => check_provider_authenticated(&provider),
+            };
+            found(status, version)
         }
         Ok(output) => {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -161,12 +928,13 @@ pub fn check_provider_available(provider: Provider) -> ProviderStatus {
             let combined = format!("{} {}", stderr, stdout);
 
             // Check if it's an auth error
-            if provider.is_auth_error(&combined) {
+            let status = if provider.is_auth_error(&combined) {
                 ProviderStatus {
                     available: false,
-                    error: Some(provider.not_logged_in_message().to_string()),
+                    error: Some(provider.not_logged_in_message()),
                     error_type: Some("not_logged_in".to_string()),
-                    auth_instructions: Some(provider.auth_instructions().to_string()),
+                    auth_instructions: Some(provider.auth_instructions()),
+                    ..Default::default()
                 }
             } else {
                 ProviderStatus {
@@ -174,87 +942,550 @@ pub fn check_provider_available(provider: Provider) -> ProviderStatus {
                     error: Some(format!("{} error: {}", provider.display_name(), stderr)),
                     error_type: Some("unknown".to_string()),
                     auth_instructions: None,
+                    ..Default::default()
                 }
-            }
+            };
+            found(status, None)
         }
-        Err(e) => ProviderStatus {
-            available: false,
-            error: Some(format!("Failed to run {}: {}", provider.display_name(), e)),
-            error_type: Some("not_installed".to_string()),
+        Err(e) => found(
+            ProviderStatus {
+                available: false,
+                error: Some(format!("Failed to run {}: {}", provider.display_name(), e)),
+                error_type: Some("not_installed".to_string()),
+                auth_instructions: None,
+                ..Default::default()
+            },
+            None,
+        ),
+    }
+}
+
+fn check_provider_authenticated(provider: &Provider) -> ProviderStatus {
+    match provider.check_authenticated() {
+        Ok(()) => ProviderStatus {
+            available: true,
+            error: None,
+            error_type: None,
             auth_instructions: None,
+            ..Default::default()
+        },
+        Err(auth_error) => ProviderStatus {
+            available: false,
+            error: Some(auth_error),
+            error_type: Some("not_logged_in".to_string()),
+            auth_instructions: Some(provider.auth_instructions()),
+            ..Default::default()
         },
     }
 }
 
-fn run_provider_process(
-    app: &AppHandle,
+#[tauri::command]
+pub fn get_provider_binary_path(provider: Provider) -> Result<Option<String>, String> {
+    settings::get_binary_path(get_db()?, provider.id())
+}
+
+/// Point a provider at a specific binary, or with `None` go back to searching for it
+#[tauri::command]
+pub fn set_provider_binary_path(provider: Provider, path: Option<String>) -> Result<(), String> {
+    let path = path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(path) = &path {
+        if !std::path::Path::new(path).is_file() {
+            return Err(format!("{} is not a file", path));
+        }
+    }
+    settings::set_binary_path(get_db()?, provider.id(), path.as_deref())
+}
+
+/// The runner a provider uses: the one chosen in settings, else its default
+#[tauri::command]
+pub fn get_provider_runner_mode(provider: Provider) -> RunnerMode {
+    provider.runner_mode()
+}
+
+/// Choose how a provider is run, or with `None` go back to its default
+#[tauri::command]
+pub fn set_provider_runner_mode(
+    provider: Provider,
+    mode: Option<RunnerMode>,
+) -> Result<(), String> {
+    settings::set_runner_mode(get_db()?, provider.id(), mode)
+}
+
+/// Everything a spawned provider run needs
+#[derive(Clone)]
+struct ProviderRun {
     provider: Provider,
-    message: &str,
+    message: String,
+    folder_path: String,
+    session_id: Option<String>,
+    user_message: Option<String>,
+    env: EnvVars,
+    /// Name of the env profile `env` came from, for the run log
+    env_profile: Option<String>,
+    launch: LaunchOptions,
+    process_id: String,
+    /// 1 for the first attempt, counting up with each retry
+    attempt: u32,
+    stop_flag: Arc<AtomicBool>,
+    output: Arc<Mutex<OutputBuffer<ProcessOutput>>>,
+    activity: Arc<Mutex<ProcessActivity>>,
+    max_duration: Option<Duration>,
+    idle_timeout: Option<Duration>,
+}
+
+/// Sent when resuming a session after a transient failure
+const RETRY_PROMPT: &str =
+    "The previous attempt was interrupted by a temporary provider error. Continue where you left off.";
+
+/// Run the provider, retrying transient failures with exponential backoff.
+/// Retries resume the session the failed attempt reported, so the agent keeps
+/// its context; without one the original message is sent again. Returns how
+/// the last attempt ended, with `ExitReason::Failed` once retries run out.
+fn run_with_retries(app: &AppHandle, mut run: ProviderRun) -> Result<RunOutcome, String> {
+    let policy = get_db()
+        .and_then(settings::get_retry_policy)
+        .unwrap_or_default();
+
+    loop {
+        let attempt = run.attempt;
+        let started_at = Utc::now().to_rfc3339();
+        let result = run_provider_process(app, run.clone());
+        record_attempt(&run.process_id, attempt, &started_at, &result);
+
+        let outcome = result?;
+        let Some(error) = outcome.transient_error.clone() else {
+            return Ok(outcome);
+        };
+        if attempt > policy.max_retries {
+            return Ok(RunOutcome {
+                reason: ExitReason::Failed,
+                ..outcome
+            });
+        }
+
+        let delay = policy.delay(attempt);
+        run.attempt += 1;
+        let _ = app.emit(
+            "ai-retry",
+            AIRetry {
+                process_id: run.process_id.clone(),
+                attempt: run.attempt,
+                max_retries: policy.max_retries,
+                delay_secs: delay.as_secs(),
+                error,
+            },
+        );
+        if !retry::sleep_unless_stopped(delay, &run.stop_flag) {
+            return Ok(RunOutcome {
+                reason: ExitReason::Stopped,
+                transient_error: None,
+                ..outcome
+            });
+        }
+
+        let session_id = AI_PROCESSES.lock().ok().and_then(|processes| {
+            processes
+                .get(&run.process_id)
+                .and_then(|p| p.info.session_id.clone())
+        });
+        if let Some(session_id) = session_id {
+            run.session_id = Some(session_id);
+            run.message = RETRY_PROMPT.to_string();
+            run.user_message = Some(RETRY_PROMPT.to_string());
+        }
+
+        // The failed attempt may have spent what was left
+        let iteration = AI_PROCESSES.lock().ok().and_then(|processes| {
+            processes
+                .get(&run.process_id)
+                .and_then(|p| p.info.ralph_iteration.clone())
+        });
+        check_budget(app, &run.folder_path, iteration.as_ref())?;
+    }
+}
+
+/// Refuse a Ralph iteration whose folder budget is spent, telling views why
+fn check_budget(
+    app: &AppHandle,
     folder_path: &str,
-    session_id: Option<&str>,
+    iteration: Option<&RalphIterationRef>,
+) -> Result<(), String> {
+    let Some(iteration) = iteration else {
+        return Ok(());
+    };
+    match budget::check_iteration_budget(get_db()?, folder_path, iteration)? {
+        Some(exceeded) => {
+            let message = exceeded.message();
+            let _ = app.emit("budget-exceeded", exceeded);
+            Err(message)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Store one attempt of a run; errors are logged, never fatal
+fn record_attempt(
     process_id: &str,
-    stop_flag: Arc<AtomicBool>,
-) -> Result<i32, String> {
+    attempt: u32,
+    started_at: &str,
+    result: &Result<RunOutcome, String>,
+) {
+    let Some(info) = AI_PROCESSES
+        .lock()
+        .ok()
+        .and_then(|processes| processes.get(process_id).map(|p| p.info.clone()))
+    else {
+        return;
+    };
+
+    let (outcome, exit_code, error) = match result {
+        Ok(RunOutcome {
+            code,
+            transient_error: Some(error),
+            ..
+        }) => ("transient_error", Some(*code), Some(error.clone())),
+        Ok(RunOutcome { code, reason, .. }) => {
+            let outcome = match reason {
+                ExitReason::Stopped => "stopped",
+                ExitReason::TimedOut => "timed_out",
+                _ => "exited",
+            };
+            (outcome, Some(*code), None)
+        }
+        Err(e) => ("error", None, Some(e.clone())),
+    };
+
+    let record = RunAttempt {
+        process_id: info.process_id,
+        attempt,
+        folder_path: info.folder_path,
+        provider: info.provider,
+        session_id: info.session_id,
+        ralph_prd: info.ralph_iteration.as_ref().map(|i| i.prd_name.clone()),
+        ralph_iteration: info.ralph_iteration.as_ref().map(|i| i.iteration_number),
+        outcome: outcome.to_string(),
+        exit_code,
+        error,
+        started_at: started_at.to_string(),
+        ended_at: Utc::now().to_rfc3339(),
+    };
+    if let Err(e) = get_db().and_then(|conn| attempts::record_attempt(conn, &record)) {
+        eprintln!("Failed to record run attempt: {}", e);
+    }
+}
+
+/// How one attempt of a run ended
+struct RunOutcome {
+    code: i32,
+    reason: ExitReason,
+    /// Set when the provider failed with an error worth retrying
+    transient_error: Option<String>,
+    /// Set when the provider failed because it isn't logged in
+    auth_error: Option<String>,
+}
+
+/// One attempt of a run, tracked like a dispatched run but without the queue,
+/// retries or Ralph bookkeeping. Returns the process ID and exit code. The
+/// end-to-end tests in `tests/` drive the run pipeline through this.
+#[cfg(feature = "replay")]
+pub fn run_single_attempt<R: Runtime>(
+    app: &AppHandle<R>,
+    provider: Provider,
+    message: String,
+    folder_path: String,
+    session_id: Option<String>,
+) -> Result<(String, i32), String> {
+    let process_id = Uuid::new_v4().to_string();
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let output = Arc::new(Mutex::new(OutputBuffer::new(
+        OUTPUT_BACKLOG_ENTRIES,
+        OUTPUT_BACKLOG_BYTES,
+    )));
+    let activity = Arc::new(Mutex::new(ProcessActivity::default()));
+    let process = AIProcess {
+        stop_flag: stop_flag.clone(),
+        output: output.clone(),
+        activity: activity.clone(),
+        info: ProcessInfo {
+            process_id: process_id.clone(),
+            provider: provider.id().to_string(),
+            folder_path: folder_path.clone(),
+            session_type: "plan".to_string(),
+            session_id: session_id.clone(),
+            model: None,
+            permission_profile: Default::default(),
+            ralph_iteration: None,
+        },
+    };
+    AI_PROCESSES
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(process_id.clone(), process);
+
+    let result = run_provider_process(
+        app,
+        ProviderRun {
+            provider,
+            user_message: Some(message.clone()),
+            message,
+            folder_path,
+            session_id,
+            env: EnvVars::new(),
+            env_profile: None,
+            launch: LaunchOptions::default(),
+            process_id: process_id.clone(),
+            attempt: 1,
+            stop_flag,
+            output,
+            activity,
+            max_duration: None,
+            idle_timeout: None,
+        },
+    );
+
+    if let Ok(mut processes) = AI_PROCESSES.lock() {
+        processes.remove(&process_id);
+    }
+    result.map(|outcome| (process_id, outcome.code))
+}
+
+/// Run the provider to completion, returning its exit code and why it ended
+fn run_provider_process<R: Runtime>(
+    app: &AppHandle<R>,
+    run: ProviderRun,
+) -> Result<RunOutcome, String> {
+    let ProviderRun {
+        provider,
+        message,
+        folder_path,
+        session_id,
+        user_message,
+        env,
+        env_profile,
+        launch,
+        process_id,
+        attempt,
+        stop_flag,
+        output,
+        activity,
+        max_duration,
+        idle_timeout,
+    } = run;
+    let session_id = session_id.as_deref();
+    let process_id = process_id.as_str();
+
     // Find binary (GUI apps don't inherit shell PATH)
     let binary_path = provider
         .find_binary()
         .ok_or_else(|| format!("{} is not installed", provider.display_name()))?;
 
-    let pty_system = native_pty_system();
+    // Build args using provider-specific logic. Long prompts go to stdin
+    // instead, keeping them off the command line.
+    let launch = LaunchOptions {
+        prompt_via_stdin: provider.prompt_via_stdin(&message),
+        ..launch
+    };
+    let args = provider.build_args(&message, session_id, &launch);
+    let runner_mode = provider.runner_mode();
 
-    let pair = pty_system
-        .openpty(PtySize {
-            rows: 24,
-            cols: 80,
-            pixel_width: 0,
-            pixel_height: 0,
-        })
-        .map_err(|e| format!("Failed to open pty: {}", e))?;
+    // Every attempt appends to the run's raw log, prompt left out
+    let mut log = RunLog::open(&folder_path, process_id);
+    log.start(&RunLogStart {
+        provider: provider.id(),
+        argv: run_log::redact_argv(&binary_path, &args, &message),
+        cwd: &folder_path,
+        env_profile: env_profile.as_deref().unwrap_or(DEFAULT_ENV_PROFILE),
+        runner: runner_mode.name(),
+    });
+    let log = Arc::new(Mutex::new(log));
 
-    let mut cmd = CommandBuilder::new(binary_path);
+    // The login shell's environment first, so the folder's profile can override it
+    let mut command_env = shell_env::login_env().clone();
+    command_env.extend(env);
 
-    // Build args using provider-specific logic
-    let args = provider.build_args(message, session_id);
-    cmd.args(&args);
-    cmd.cwd(folder_path);
+    // Persist messages as they stream. A resumed session is known up front;
+    // a new one starts recording once the provider reports its ID.
+    let recorder = Arc::new(Mutex::new(RunRecorder::new(user_message)));
+    if let (Some(session_id), Ok(mut recorder)) = (session_id, recorder.lock()) {
+        recorder.start_session(session_id);
+    }
 
-    let mut child = pair
-        .slave
-        .spawn_command(cmd)
-        .map_err(|e| format!("Failed to spawn {}: {}", provider.display_name(), e))?;
+    let command = ProviderCommand {
+        binary_path,
+        args,
+        folder_path: &folder_path,
+        env: command_env,
+        stdin: launch.prompt_via_stdin.then_some(message),
+    };
+    let spawned = match runner_mode {
+        RunnerMode::Pty => spawn_pty(command, provider.display_name()),
+        RunnerMode::Pipe => {
+            let app = app.clone();
+            let stderr_provider = provider.clone();
+            let process_id = process_id.to_string();
+            let recorder = recorder.clone();
+            let log = log.clone();
+            let output = output.clone();
+            let activity = activity.clone();
+            spawn_pipe(command, provider.display_name(), move |line| {
+                if let Ok(mut log) = log.lock() {
+                    log.stderr(line);
+                }
+                if let Ok(mut recorder) = recorder.lock() {
+                    recorder.observe_error(&stderr_provider, line);
+                }
+                let data = format!("{}\n", line);
+                if let Ok(mut activity) = activity.lock() {
+                    activity.record_output(data.len());
+                }
+                let offset = buffer_output(
+                    &output,
+                    ProcessOutput::Stderr { data: data.clone() },
+                    data.len(),
+                );
+                let _ = app.emit(
+                    "ai-stderr",
+                    AIStderr {
+                        process_id: process_id.clone(),
+                        offset,
+                        data,
+                    },
+                );
+            })
+        }
+    };
+    let spawned = spawned.inspect_err(|e| {
+        if let Ok(mut log) = log.lock() {
+            log.end(None, &format!("failed to start: {}", e));
+        }
+    })?;
+    let SpawnedProvider {
+        mut child,
+        output: mut reader,
+        stderr,
+        _keep_alive,
+    } = spawned;
+    let strict = runner_mode == RunnerMode::Pipe;
+    if let Ok(mut activity) = activity.lock() {
+        activity.pid = child.process_id();
+    }
 
-    // Drop slave so EOF is sent when master closes
-    drop(pair.slave);
+    // Announced once there's a pid to show; retries report their new pid
+    let info = AI_PROCESSES
+        .lock()
+        .ok()
+        .and_then(|processes| processes.get(process_id).map(|p| p.info.clone()));
+    if let Some(info) = info {
+        let event = if attempt == 1 {
+            "process-started"
+        } else {
+            "process-updated"
+        };
+        let _ = app.emit(event, describe_process(info, &activity));
+    }
 
-    // Get reader from master
-    let mut reader = pair
-        .master
-        .try_clone_reader()
-        .map_err(|e| format!("Failed to clone reader: {}", e))?;
+    // Cleared once output ends; background helpers below stop with it
+    let running = Arc::new(AtomicBool::new(true));
 
-    // Keep master alive until we're done (dropping it signals EOF to child)
-    let _master = pair.master;
+    // Commit quiet periods too, so a crash mid tool call keeps recent output
+    {
+        let recorder = recorder.clone();
+        let running = running.clone();
+        std::thread::spawn(move || {
+            while running.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(500));
+                if let Ok(mut recorder) = recorder.lock() {
+                    recorder.flush_if_due();
+                }
+            }
+        });
+    }
+
+    // Milliseconds after `started` at which output last arrived
+    let started = Instant::now();
+    let last_output_ms = Arc::new(AtomicU64::new(0));
 
-    // Stream output in real-time
+    // Watchdog: acts on Stop and timeouts right away rather than between
+    // reads, which can block for as long as the provider stays silent
+    let stop_finished = Arc::new(AtomicBool::new(false));
+    let watchdog = {
+        let app = app.clone();
+        let process_id = process_id.to_string();
+        let running = running.clone();
+        let stop_flag = stop_flag.clone();
+        let stop_finished = stop_finished.clone();
+        let last_output_ms = last_output_ms.clone();
+        let pid = child.process_id();
+        let mut killer = child.clone_killer();
+        let grace = get_db()
+            .and_then(settings::get_stop_grace)
+            .unwrap_or_default();
+        std::thread::spawn(move || {
+            while running.load(Ordering::SeqCst) {
+                let reason = if stop_flag.load(Ordering::SeqCst) {
+                    Some(ExitReason::Stopped)
+                } else {
+                    let elapsed = started.elapsed();
+                    let idle = elapsed.saturating_sub(Duration::from_millis(
+                        last_output_ms.load(Ordering::SeqCst),
+                    ));
+                    let exceeded = match (max_duration, idle_timeout) {
+                        (Some(limit), _) if elapsed >= limit => {
+                            Some((TimeoutKind::MaxDuration, limit))
+                        }
+                        (_, Some(limit)) if idle >= limit => Some((TimeoutKind::Idle, limit)),
+                        _ => None,
+                    };
+                    exceeded.map(|(kind, limit)| {
+                        let _ = app.emit(
+                            "ai-timeout",
+                            AITimeout {
+                                process_id: process_id.clone(),
+                                kind,
+                                limit_secs: limit.as_secs(),
+                                elapsed_secs: elapsed.as_secs(),
+                            },
+                        );
+                        ExitReason::TimedOut
+                    })
+                };
+
+                if let Some(reason) = reason {
+                    stop_child(pid, killer.as_mut(), grace, &running);
+                    stop_finished.store(true, Ordering::SeqCst);
+                    return Some(reason);
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            None
+        })
+    };
+
+    // Stream output in real-time, one complete line at a time so the provider
+    // can rewrite its own dialect before it reaches the frontend. The decoder
+    // carries partial lines and split UTF-8 characters across reads.
     let mut buf = [0u8; 256];
+    let mut decoder = LineDecoder::new();
     loop {
-        // Check if stop was requested
-        if stop_flag.load(Ordering::SeqCst) {
-            // Kill the child process
-            let _ = child.kill();
+        // Normally the stopped process closes the pty; don't wait on anything it left behind
+        if stop_finished.load(Ordering::SeqCst) {
             break;
         }
 
         match reader.read(&mut buf) {
             Ok(0) => break, // EOF
             Ok(n) => {
-                if let Ok(text) = std::str::from_utf8(&buf[..n]) {
-                    let _ = app.emit(
-                        "ai-output",
-                        AIOutput {
-                            process_id: process_id.to_string(),
-                            data: text.to_string(),
-                        },
+                let now_ms = started.elapsed().as_millis().try_into().unwrap_or(u64::MAX);
+                last_output_ms.store(now_ms, Ordering::SeqCst);
+                if let Ok(mut log) = log.lock() {
+                    log.output(&buf[..n]);
+                }
+                if let Ok(mut activity) = activity.lock() {
+                    activity.record_output(n);
+                }
+                for line in decoder.push(&buf[..n]) {
+                    emit_line(
+                        app, &provider, process_id, &recorder, &output, &line, strict,
                     );
                 }
             }
@@ -274,9 +1505,555 @@ fn run_provider_process(
         }
     }
 
+    // Flush a trailing line that had no newline
+    if let Some(line) = decoder.finish() {
+        emit_line(
+            app, &provider, process_id, &recorder, &output, &line, strict,
+        );
+    }
+
+    running.store(false, Ordering::SeqCst);
+    let (mut transient_error, mut auth_error) = (None, None);
+    if let Ok(mut recorder) = recorder.lock() {
+        recorder.flush();
+        recorder.record_usage(process_id);
+        transient_error = recorder.transient_error.take();
+        auth_error = recorder.auth_error.take();
+    }
+
+    // A stop requested just as output ended still counts as stopped
+    let reason = watchdog.join().ok().flatten().unwrap_or_else(|| {
+        if stop_flag.load(Ordering::SeqCst) {
+            ExitReason::Stopped
+        } else {
+            ExitReason::Exited
+        }
+    });
+
     let status = child
         .wait()
         .map_err(|e| format!("Failed to wait for process: {}", e))?;
+    if let Ok(mut activity) = activity.lock() {
+        activity.pid = None;
+    }
+
+    // Let stderr drain so its errors count toward the outcome, without
+    // waiting on anything the provider left running with it open
+    if let Some(stderr) = stderr {
+        let deadline = Instant::now() + Duration::from_millis(500);
+        while !stderr.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    let code = status.exit_code().try_into().unwrap_or(-1);
+    if let Ok(mut log) = log.lock() {
+        log.end(Some(code), reason.name());
+    }
+
+    // Only a run that failed on its own is retried; an error the provider
+    // recovered from doesn't count
+    let failed = code != 0 || recorder.lock().is_ok_and(|r| r.failed);
+    let failed = failed && matches!(reason, ExitReason::Exited);
+
+    Ok(RunOutcome {
+        code,
+        reason,
+        transient_error: transient_error.filter(|_| failed),
+        auth_error: auth_error.filter(|_| failed),
+    })
+}
+
+/// A provider command line, ready for either runner
+struct ProviderCommand<'a> {
+    binary_path: PathBuf,
+    args: Vec<String>,
+    folder_path: &'a str,
+    /// The complete environment: the login shell's, overlaid with the folder's profile
+    env: EnvVars,
+    /// Written to stdin, for prompts too long for the command line
+    stdin: Option<String>,
+}
+
+/// A running provider, whichever runner started it
+struct SpawnedProvider {
+    child: Box<dyn Child + Send + Sync>,
+    /// Stdout, or everything the terminal shows on a PTY
+    output: Box<dyn Read + Send>,
+    /// The thread reporting stderr, when it is read separately
+    stderr: Option<JoinHandle<()>>,
+    /// Held until the run ends: the PTY master (dropping it signals EOF to the
+    /// child) and the prompt pipe
+    _keep_alive: Box<dyn Send>,
+}
+
+/// Start a provider on a pseudo-terminal. A prompt for stdin goes through a
+/// named pipe, since the terminal can't carry it.
+fn spawn_pty(command: ProviderCommand, display_name: &str) -> Result<SpawnedProvider, String> {
+    let pty_system = native_pty_system();
+
+    let pair = pty_system
+        .openpty(PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|e| format!("Failed to open pty: {}", e))?;
+
+    #[cfg(unix)]
+    let prompt_pipe = command
+        .stdin
+        .is_some()
+        .then(PromptPipe::create)
+        .transpose()?;
+    #[cfg(unix)]
+    let mut cmd = match &prompt_pipe {
+        Some(pipe) => {
+            let (program, args) = pipe.wrap_command(&command.binary_path, &command.args);
+            let mut cmd = CommandBuilder::new(program);
+            cmd.args(&args);
+            cmd
+        }
+        None => {
+            let mut cmd = CommandBuilder::new(&command.binary_path);
+            cmd.args(&command.args);
+            cmd
+        }
+    };
+    #[cfg(not(unix))]
+    let mut cmd = {
+        let mut cmd = CommandBuilder::new(&command.binary_path);
+        cmd.args(&command.args);
+        cmd
+    };
+    cmd.cwd(command.folder_path);
+    for (key, value) in &command.env {
+        cmd.env(key, value);
+    }
+
+    let child = pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|e| format!("Failed to spawn {}: {}", display_name, e))?;
+
+    // Drop slave so EOF is sent when master closes
+    drop(pair.slave);
+
+    #[cfg(unix)]
+    if let (Some(pipe), Some(message)) = (&prompt_pipe, command.stdin) {
+        pipe.spawn_writer(message);
+    }
+
+    // Get reader from master
+    let output = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| format!("Failed to clone reader: {}", e))?;
+
+    #[cfg(unix)]
+    let keep_alive: Box<dyn Send> = Box::new((pair.master, prompt_pipe));
+    #[cfg(not(unix))]
+    let keep_alive: Box<dyn Send> = Box::new(pair.master);
+
+    Ok(SpawnedProvider {
+        child,
+        output,
+        stderr: None,
+        _keep_alive: keep_alive,
+    })
+}
+
+/// Start a provider on plain pipes, passing each line of stderr to `on_stderr`
+fn spawn_pipe(
+    command: ProviderCommand,
+    display_name: &str,
+    mut on_stderr: impl FnMut(&str) + Send + 'static,
+) -> Result<SpawnedProvider, String> {
+    let mut cmd = std::process::Command::new(&command.binary_path);
+    cmd.args(&command.args)
+        .current_dir(command.folder_path)
+        .env_clear()
+        .envs(&command.env)
+        .stdin(if command.stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // Lead a process group, as on a PTY, so stopping reaches the tools it starts
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to spawn {}: {}", display_name, e))?;
+
+    if let (Some(mut stdin), Some(message)) = (child.stdin.take(), command.stdin) {
+        std::thread::spawn(move || {
+            // A provider that exits without reading closes the pipe on us
+            if let Err(e) = stdin.write_all(message.as_bytes()) {
+                if e.kind() != std::io::ErrorKind::BrokenPipe {
+                    eprintln!("Failed to write prompt to stdin: {}", e);
+                }
+            }
+        });
+    }
+
+    let output = child
+        .stdout
+        .take()
+        .ok_or_else(|| "Failed to capture stdout".to_string())?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| "Failed to capture stderr".to_string())?;
+    let stderr = std::thread::spawn(move || {
+        let mut buf = [0u8; 1024];
+        let mut decoder = LineDecoder::new();
+        while let Ok(n) = stderr.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            for line in decoder.push(&buf[..n]) {
+                on_stderr(&line);
+            }
+        }
+        if let Some(line) = decoder.finish() {
+            on_stderr(&line);
+        }
+    });
+
+    Ok(SpawnedProvider {
+        child: Box::new(child),
+        output: Box::new(output),
+        stderr: Some(stderr),
+        _keep_alive: Box::new(()),
+    })
+}
+
+/// Stop a provider and everything it spawned. The PTY makes the provider a
+/// process group leader, so on Unix the whole group is signalled with
+/// escalation; elsewhere the child is killed directly.
+fn stop_child(
+    pid: Option<u32>,
+    killer: &mut dyn ChildKiller,
+    grace: StopGrace,
+    running: &AtomicBool,
+) {
+    #[cfg(unix)]
+    if let Some(pid) = pid {
+        process_group::terminate_group(pid, grace, || !running.load(Ordering::SeqCst));
+        return;
+    }
+
+    let _ = (pid, grace, running);
+    let _ = killer.kill();
+}
+
+/// Translate one line of provider output, emit it as `ai-output`, and emit the
+/// normalized records parsed from it as `ai-event`
+fn emit_line<R: Runtime>(
+    app: &AppHandle<R>,
+    provider: &Provider,
+    process_id: &str,
+    recorder: &Mutex<RunRecorder>,
+    output: &Mutex<OutputBuffer<ProcessOutput>>,
+    line: &str,
+    strict: bool,
+) {
+    let Some(data) = provider.translate_line(line) else {
+        return;
+    };
+
+    // Piped stdout carries nothing but NDJSON records; anything else is
+    // left to the run log
+    if strict && serde_json::from_str::<Value>(data.trim()).is_err() {
+        return;
+    }
+
+    let Ok(mut recorder) = recorder.lock() else {
+        return;
+    };
+
+    for event in stream::parse_line(&data) {
+        match &event {
+            StreamEvent::Init { session_id, model } => {
+                record_session(app, process_id, session_id, model.as_deref());
+                recorder.start_session(session_id);
+            }
+            StreamEvent::Result {
+                is_error,
+                result,
+                error,
+                ..
+            } => {
+                recorder.failed = *is_error;
+                if let Some(message) = error.as_ref().or(result.as_ref()).filter(|_| *is_error) {
+                    recorder.observe_error(provider, message);
+                }
+            }
+            // Errors some CLIs print as plain text
+            StreamEvent::Unknown {
+                raw: Value::String(line),
+            } => recorder.observe_error(provider, line),
+            _ => {}
+        }
+        recorder.usage.observe(&event);
+
+        let offset = buffer_output(
+            output,
+            ProcessOutput::Event {
+                event: event.clone(),
+            },
+            data.len(),
+        );
+        let _ = app.emit(
+            "ai-event",
+            AIEvent {
+                process_id: process_id.to_string(),
+                offset,
+                event,
+            },
+        );
+    }
+
+    recorder.record(&data);
+
+    let data = format!("{}\n", data);
+    let offset = buffer_output(
+        output,
+        ProcessOutput::Output { data: data.clone() },
+        data.len(),
+    );
+    let _ = app.emit(
+        "ai-output",
+        AIOutput {
+            process_id: process_id.to_string(),
+            offset,
+            data,
+        },
+    );
+}
+
+/// Keep what a process produced in its backlog, returning the offset to send
+/// with the live event
+fn buffer_output(
+    output: &Mutex<OutputBuffer<ProcessOutput>>,
+    entry: ProcessOutput,
+    size: usize,
+) -> u64 {
+    output
+        .lock()
+        .map(|mut output| output.push(entry, size))
+        .unwrap_or_default()
+}
+
+/// Records what a run produced: its messages, saved to the session they
+/// belong to, and the token usage it reports
+struct RunRecorder {
+    batcher: Option<MessageBatcher>,
+    /// Saved as the first message once the session is known
+    user_message: Option<String>,
+    usage: UsageTracker,
+    /// Whether the provider's final result was an error
+    failed: bool,
+    /// The last rate limit, overload or server error the provider reported
+    transient_error: Option<String>,
+    /// The last authentication error the provider reported
+    auth_error: Option<String>,
+}
+
+impl RunRecorder {
+    fn new(user_message: Option<String>) -> Self {
+        Self {
+            batcher: None,
+            user_message,
+            usage: UsageTracker::new(),
+            failed: false,
+            transient_error: None,
+            auth_error: None,
+        }
+    }
+
+    /// Remember an error worth acting on once the run ends
+    fn observe_error(&mut self, provider: &Provider, message: &str) {
+        if provider.is_transient_error(message) {
+            self.transient_error = Some(message.to_string());
+        } else if provider.is_auth_error(message) {
+            self.auth_error = Some(message.to_string());
+        }
+    }
 
-    Ok(status.exit_code().try_into().unwrap_or(-1))
+    /// Record into `session_id` from now on, committing the previous session's messages
+    fn start_session(&mut self, session_id: &str) {
+        if self
+            .batcher
+            .as_ref()
+            .is_some_and(|b| b.session_id() == session_id)
+        {
+            return;
+        }
+        self.flush();
+
+        let batcher = get_db().map(|conn| MessageBatcher::new(conn, session_id));
+        self.batcher = match batcher {
+            Ok(batcher) => Some(batcher),
+            Err(e) => {
+                eprintln!("Failed to record messages for {}: {}", session_id, e);
+                None
+            }
+        };
+
+        if let Some(message) = self.user_message.take() {
+            self.push(
+                "user",
+                json!({ "type": "user", "content": message }).to_string(),
+            );
+        }
+    }
+
+    /// Queue one line of (translated) provider output; non-JSON lines aren't saved
+    fn record(&mut self, data: &str) {
+        let Ok(record) = serde_json::from_str::<Value>(data.trim()) else {
+            return;
+        };
+        let message_type = record
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown");
+
+        // Top-level user records echo the prompt (already saved from user_message)
+        let parent = record.get("parent_tool_use_id").filter(|v| !v.is_null());
+        if message_type == "user" && parent.is_none() {
+            return;
+        }
+
+        self.push(message_type, data.trim().to_string());
+    }
+
+    fn push(&mut self, message_type: &str, message_json: String) {
+        if let Some(batcher) = self.batcher.as_mut() {
+            if let Err(e) = batcher.push(message_type, message_json) {
+                eprintln!("Failed to save messages: {}", e);
+            }
+        }
+    }
+
+    /// Store the run's usage totals, if it reported any
+    fn record_usage(&self, process_id: &str) {
+        let Some((totals, cost_usd)) = self.usage.totals() else {
+            return;
+        };
+        let Some(info) = AI_PROCESSES
+            .lock()
+            .ok()
+            .and_then(|processes| processes.get(process_id).map(|p| p.info.clone()))
+        else {
+            return;
+        };
+
+        let record = UsageRecord {
+            session_id: info.session_id,
+            process_id: info.process_id,
+            folder_path: info.folder_path,
+            provider: info.provider,
+            ralph_prd: info.ralph_iteration.as_ref().map(|i| i.prd_name.clone()),
+            ralph_iteration: info.ralph_iteration.as_ref().map(|i| i.iteration_number),
+            input_tokens: totals.input_tokens,
+            output_tokens: totals.output_tokens,
+            cache_creation_input_tokens: totals.cache_creation_input_tokens,
+            cache_read_input_tokens: totals.cache_read_input_tokens,
+            cost_usd,
+        };
+        if let Err(e) = get_db().and_then(|conn| usage::record_usage(conn, &record)) {
+            eprintln!("Failed to record usage: {}", e);
+        }
+    }
+
+    fn flush_if_due(&mut self) {
+        if self.batcher.as_ref().is_some_and(MessageBatcher::is_due) {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(batcher) = self.batcher.as_mut() {
+            if let Err(e) = batcher.flush() {
+                eprintln!("Failed to save messages: {}", e);
+            }
+        }
+    }
+}
+
+/// Persist a session ID as soon as the provider reports it: create the session
+/// row with its model and permission profile, link it to the Ralph iteration (if any), and notify views
+fn record_session<R: Runtime>(
+    app: &AppHandle<R>,
+    process_id: &str,
+    session_id: &str,
+    model: Option<&str>,
+) {
+    let info = {
+        let Ok(mut processes) = AI_PROCESSES.lock() else {
+            return;
+        };
+        let Some(process) = processes.get_mut(process_id) else {
+            return;
+        };
+        process.info.session_id = Some(session_id.to_string());
+        // The provider's report is more specific than an alias we asked for
+        if let Some(model) = model {
+            process.info.model = Some(model.to_string());
+        }
+        process.info.clone()
+    };
+
+    if let Ok(conn) = get_db() {
+        if let Err(e) = sessions::create_session(
+            conn,
+            session_id,
+            &info.folder_path,
+            &info.provider,
+            &info.session_type,
+        ) {
+            eprintln!("Failed to record session {}: {}", session_id, e);
+        }
+
+        if let Some(model) = &info.model {
+            if let Err(e) = sessions::set_session_model(conn, session_id, model) {
+                eprintln!("Failed to record model for session {}: {}", session_id, e);
+            }
+        }
+
+        if let Err(e) =
+            sessions::set_session_permission_profile(conn, session_id, &info.permission_profile)
+        {
+            eprintln!(
+                "Failed to record permission profile for session {}: {}",
+                session_id, e
+            );
+        }
+
+        if let Some(iteration) = &info.ralph_iteration {
+            if let Err(e) = iterations::update_ralph_iteration_session_id(
+                conn,
+                &info.folder_path,
+                &iteration.prd_name,
+                iteration.iteration_number,
+                session_id,
+            ) {
+                eprintln!("Failed to link session {} to iteration: {}", session_id, e);
+            }
+        }
+    }
+
+    let _ = app.emit(
+        "ai-session",
+        AISession {
+            process_id: process_id.to_string(),
+            session_id: session_id.to_string(),
+        },
+    );
 }
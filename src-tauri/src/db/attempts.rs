@@ -0,0 +1,178 @@
+use super::DbConnection;
+use rusqlite::{params, Row, ToSql};
+use serde::Serialize;
+
+/// One attempt of a provider run. A run that hits a transient failure is
+/// retried under the same process ID, so it has one attempt per try.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct RunAttempt {
+    pub process_id: String,
+    /// 1 for the first try
+    pub attempt: u32,
+    pub folder_path: String,
+    pub provider: String,
+    pub session_id: Option<String>,
+    pub ralph_prd: Option<String>,
+    pub ralph_iteration: Option<i32>,
+    /// "exited", "stopped", "timed_out", "transient_error" or "error"
+    pub outcome: String,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    pub started_at: String,
+    pub ended_at: String,
+}
+
+/// Record an attempt once it has ended
+pub fn record_attempt(conn: &DbConnection, attempt: &RunAttempt) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    conn.execute(
+        "INSERT INTO run_attempts (process_id, attempt, folder_path, provider, session_id, ralph_prd,
+            ralph_iteration, outcome, exit_code, error, started_at, ended_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            attempt.process_id,
+            attempt.attempt,
+            attempt.folder_path,
+            attempt.provider,
+            attempt.session_id,
+            attempt.ralph_prd,
+            attempt.ralph_iteration,
+            attempt.outcome,
+            attempt.exit_code,
+            attempt.error,
+            attempt.started_at,
+            attempt.ended_at
+        ],
+    )
+    .map_err(|e| format!("Failed to record run attempt: {}", e))?;
+
+    Ok(())
+}
+
+fn row_to_attempt(row: &Row) -> rusqlite::Result<RunAttempt> {
+    Ok(RunAttempt {
+        process_id: row.get(0)?,
+        attempt: row.get(1)?,
+        folder_path: row.get(2)?,
+        provider: row.get(3)?,
+        session_id: row.get(4)?,
+        ralph_prd: row.get(5)?,
+        ralph_iteration: row.get(6)?,
+        outcome: row.get(7)?,
+        exit_code: row.get(8)?,
+        error: row.get(9)?,
+        started_at: row.get(10)?,
+        ended_at: row.get(11)?,
+    })
+}
+
+/// Attempts matching `filter` (an SQL condition over `params`), oldest first
+fn query_attempts(
+    conn: &DbConnection,
+    filter: &str,
+    params: &[&dyn ToSql],
+) -> Result<Vec<RunAttempt>, String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT process_id, attempt, folder_path, provider, session_id, ralph_prd, ralph_iteration,
+                    outcome, exit_code, error, started_at, ended_at
+             FROM run_attempts WHERE {} ORDER BY id",
+            filter
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let attempts = stmt
+        .query_map(params, row_to_attempt)
+        .map_err(|e| format!("Failed to query run attempts: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read row: {}", e))?;
+
+    Ok(attempts)
+}
+
+/// Get every attempt of a run
+pub fn get_process_attempts(
+    conn: &DbConnection,
+    process_id: &str,
+) -> Result<Vec<RunAttempt>, String> {
+    query_attempts(conn, "process_id = ?1", &[&process_id])
+}
+
+/// Get every attempt of every run in a session
+pub fn get_session_attempts(
+    conn: &DbConnection,
+    session_id: &str,
+) -> Result<Vec<RunAttempt>, String> {
+    query_attempts(conn, "session_id = ?1", &[&session_id])
+}
+
+/// Get every attempt made for a Ralph iteration
+pub fn get_iteration_attempts(
+    conn: &DbConnection,
+    folder_path: &str,
+    prd_name: &str,
+    iteration_number: i32,
+) -> Result<Vec<RunAttempt>, String> {
+    query_attempts(
+        conn,
+        "folder_path = ?1 AND ralph_prd = ?2 AND ralph_iteration = ?3",
+        &[&folder_path, &prd_name, &iteration_number],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_db;
+
+    #[test]
+    fn test_attempts_round_trip() {
+        let conn = test_db();
+        let first = RunAttempt {
+            process_id: "p1".to_string(),
+            attempt: 1,
+            folder_path: "/work".to_string(),
+            provider: "claude_code".to_string(),
+            session_id: Some("s1".to_string()),
+            ralph_prd: Some("prd".to_string()),
+            ralph_iteration: Some(2),
+            outcome: "transient_error".to_string(),
+            exit_code: Some(1),
+            error: Some("API Error: 529 overloaded".to_string()),
+            started_at: "2025-01-01T00:00:00Z".to_string(),
+            ended_at: "2025-01-01T00:01:00Z".to_string(),
+        };
+        let second = RunAttempt {
+            attempt: 2,
+            outcome: "exited".to_string(),
+            exit_code: Some(0),
+            error: None,
+            ..first.clone()
+        };
+        record_attempt(&conn, &first).unwrap();
+        record_attempt(&conn, &second).unwrap();
+        record_attempt(
+            &conn,
+            &RunAttempt {
+                process_id: "p2".to_string(),
+                ralph_iteration: Some(3),
+                ..first.clone()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            get_process_attempts(&conn, "p1").unwrap(),
+            vec![first.clone(), second.clone()]
+        );
+        assert_eq!(
+            get_iteration_attempts(&conn, "/work", "prd", 2).unwrap(),
+            vec![first, second]
+        );
+        assert_eq!(get_session_attempts(&conn, "s1").unwrap().len(), 3);
+        assert!(get_process_attempts(&conn, "missing").unwrap().is_empty());
+    }
+}
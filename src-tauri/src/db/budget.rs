@@ -0,0 +1,239 @@
+use super::iterations;
+use super::settings::{self, FolderBudget};
+use super::usage::{self, DateRange};
+use super::DbConnection;
+use crate::models::RalphIterationRef;
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use serde::Serialize;
+
+/// Which budget ran out
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetKind {
+    DailyCost,
+    PrdTokens,
+    RunIterations,
+}
+
+/// A budget that refuses further iterations (the `budget-exceeded` event payload)
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BudgetExceeded {
+    pub folder_path: String,
+    pub prd_name: String,
+    pub budget: BudgetKind,
+    pub limit: f64,
+    pub used: f64,
+}
+
+impl BudgetExceeded {
+    pub fn message(&self) -> String {
+        match self.budget {
+            BudgetKind::DailyCost => format!(
+                "Daily budget reached: ${:.2} of ${:.2} spent today",
+                self.used, self.limit
+            ),
+            BudgetKind::PrdTokens => format!(
+                "Token budget for {} reached: {} of {} tokens used",
+                self.prd_name, self.used, self.limit
+            ),
+            BudgetKind::RunIterations => format!(
+                "Iteration budget reached: {} of {} iterations in this run",
+                self.used, self.limit
+            ),
+        }
+    }
+}
+
+/// Check whether a Ralph iteration may start under the folder's budget
+pub fn check_iteration_budget(
+    conn: &DbConnection,
+    folder_path: &str,
+    iteration: &RalphIterationRef,
+) -> Result<Option<BudgetExceeded>, String> {
+    let budget = settings::get_folder_budget(conn, folder_path)?;
+    if budget == FolderBudget::default() {
+        return Ok(None);
+    }
+
+    let exceeded = |budget: BudgetKind, limit: f64, used: f64| {
+        Some(BudgetExceeded {
+            folder_path: folder_path.to_string(),
+            prd_name: iteration.prd_name.clone(),
+            budget,
+            limit,
+            used,
+        })
+    };
+
+    if let Some(limit) = budget.max_iterations_per_run {
+        // Counted from the iterations table, never from what the caller says
+        let start = iterations::get_run_start_iteration(
+            conn,
+            folder_path,
+            &iteration.prd_name,
+            iteration.iteration_number,
+        )?
+        .ok_or_else(|| {
+            format!(
+                "Iteration {} of {} isn't saved; can't check its iteration budget",
+                iteration.iteration_number, iteration.prd_name
+            )
+        })?;
+        let used = iteration.iteration_number - start;
+        if used >= limit {
+            return Ok(exceeded(
+                BudgetKind::RunIterations,
+                limit.into(),
+                used.into(),
+            ));
+        }
+    }
+
+    if let Some(limit) = budget.max_tokens_per_prd {
+        let used = usage::get_prd_usage(
+            conn,
+            folder_path,
+            &iteration.prd_name,
+            &DateRange::default(),
+        )?
+        .total_tokens();
+        if used >= limit {
+            return Ok(exceeded(BudgetKind::PrdTokens, limit as f64, used as f64));
+        }
+    }
+
+    if let Some(limit) = budget.max_daily_cost_usd {
+        let today = DateRange::since(start_of_local_day());
+        let used = usage::get_folder_usage(conn, folder_path, &today)?.cost_usd;
+        if used >= limit {
+            return Ok(exceeded(BudgetKind::DailyCost, limit, used));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Local midnight, or UTC midnight if the local one doesn't exist
+fn start_of_local_day() -> DateTime<Utc> {
+    Local::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        .map(|midnight| midnight.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc::now().date_naive().and_time(NaiveTime::MIN).and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_db;
+    use crate::db::usage::UsageRecord;
+
+    fn iteration(number: i32) -> RalphIterationRef {
+        RalphIterationRef {
+            prd_name: "prd".to_string(),
+            iteration_number: number,
+        }
+    }
+
+    fn save_iteration(conn: &DbConnection, number: i32, status: &str) {
+        iterations::save_ralph_iteration(conn, "/work", "prd", number, status).unwrap();
+    }
+
+    fn spend(conn: &DbConnection, tokens: u64, cost: f64) {
+        let record = UsageRecord {
+            process_id: "p".to_string(),
+            folder_path: "/work".to_string(),
+            provider: "claude_code".to_string(),
+            ralph_prd: Some("prd".to_string()),
+            ralph_iteration: Some(1),
+            input_tokens: tokens,
+            cost_usd: Some(cost),
+            ..Default::default()
+        };
+        usage::record_usage(conn, &record).unwrap();
+    }
+
+    fn set_budget(conn: &DbConnection, budget: FolderBudget) {
+        settings::set_folder_budget(conn, "/work", &budget).unwrap();
+    }
+
+    #[test]
+    fn test_no_budget_never_exceeded() {
+        let conn = test_db();
+        spend(&conn, 1_000_000, 100.0);
+        assert_eq!(
+            check_iteration_budget(&conn, "/work", &iteration(50)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_run_iterations_budget() {
+        let conn = test_db();
+        set_budget(
+            &conn,
+            FolderBudget {
+                max_iterations_per_run: Some(3),
+                ..Default::default()
+            },
+        );
+
+        // An iteration the budget can't place in a run is refused
+        assert!(check_iteration_budget(&conn, "/work", &iteration(1)).is_err());
+
+        // An earlier loop stopped at iteration 4; this one started at 5
+        for number in 1..=3 {
+            save_iteration(&conn, number, "completed");
+        }
+        save_iteration(&conn, 4, "stopped");
+        save_iteration(&conn, 5, "completed");
+        save_iteration(&conn, 6, "timed_out");
+        save_iteration(&conn, 7, "running");
+        assert_eq!(
+            check_iteration_budget(&conn, "/work", &iteration(7)).unwrap(),
+            None
+        );
+
+        save_iteration(&conn, 7, "completed");
+        save_iteration(&conn, 8, "running");
+        let exceeded = check_iteration_budget(&conn, "/work", &iteration(8))
+            .unwrap()
+            .unwrap();
+        assert_eq!(exceeded.budget, BudgetKind::RunIterations);
+        assert_eq!((exceeded.limit, exceeded.used), (3.0, 3.0));
+    }
+
+    #[test]
+    fn test_token_and_cost_budgets() {
+        let conn = test_db();
+        set_budget(
+            &conn,
+            FolderBudget {
+                max_tokens_per_prd: Some(1000),
+                max_daily_cost_usd: Some(5.0),
+                ..Default::default()
+            },
+        );
+
+        spend(&conn, 400, 3.0);
+        assert_eq!(
+            check_iteration_budget(&conn, "/work", &iteration(2)).unwrap(),
+            None
+        );
+
+        spend(&conn, 100, 2.5);
+        let exceeded = check_iteration_budget(&conn, "/work", &iteration(3))
+            .unwrap()
+            .unwrap();
+        assert_eq!(exceeded.budget, BudgetKind::DailyCost);
+        assert_eq!(exceeded.used, 5.5);
+
+        spend(&conn, 600, 0.0);
+        let exceeded = check_iteration_budget(&conn, "/work", &iteration(4))
+            .unwrap()
+            .unwrap();
+        assert_eq!(exceeded.budget, BudgetKind::PrdTokens);
+        assert_eq!(exceeded.used, 1100.0);
+    }
+}
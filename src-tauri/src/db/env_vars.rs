@@ -0,0 +1,178 @@
+use super::DbConnection;
+use crate::utils::env_file::{self, EnvProfiles, EnvVars, DEFAULT_ENV_PROFILE};
+use chrono::Utc;
+use rusqlite::params;
+
+/// Get every stored variable for a folder, by profile
+pub fn get_env_vars(conn: &DbConnection, folder_path: &str) -> Result<EnvProfiles, String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    let mut stmt = conn
+        .prepare("SELECT profile, key, value FROM folder_env_vars WHERE folder_path = ?1")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let rows = stmt
+        .query_map(params![folder_path], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to query env vars: {}", e))?;
+
+    let mut profiles = EnvProfiles::new();
+    for row in rows {
+        let (profile, key, value) = row.map_err(|e| format!("Failed to read row: {}", e))?;
+        profiles.entry(profile).or_default().insert(key, value);
+    }
+
+    Ok(profiles)
+}
+
+/// Store a variable in a folder's profile, replacing any previous value
+pub fn set_env_var(
+    conn: &DbConnection,
+    folder_path: &str,
+    profile: &str,
+    key: &str,
+    value: &str,
+) -> Result<(), String> {
+    if profile.trim().is_empty() {
+        return Err("Env profile name cannot be empty".to_string());
+    }
+    if !env_file::is_valid_key(key) {
+        return Err(format!("Invalid environment variable name '{}'", key));
+    }
+
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO folder_env_vars (folder_path, profile, key, value, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(folder_path, profile, key) DO UPDATE SET
+            value = excluded.value,
+            updated_at = excluded.updated_at",
+        params![folder_path, profile.trim(), key, value, now],
+    )
+    .map_err(|e| format!("Failed to set env var {}: {}", key, e))?;
+
+    Ok(())
+}
+
+/// Remove a variable from a folder's profile
+pub fn delete_env_var(
+    conn: &DbConnection,
+    folder_path: &str,
+    profile: &str,
+    key: &str,
+) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    conn.execute(
+        "DELETE FROM folder_env_vars WHERE folder_path = ?1 AND profile = ?2 AND key = ?3",
+        params![folder_path, profile, key],
+    )
+    .map_err(|e| format!("Failed to delete env var {}: {}", key, e))?;
+
+    Ok(())
+}
+
+/// Names of the profiles defined for a folder, in `.trellico/env` or the database
+pub fn list_env_profiles(conn: &DbConnection, folder_path: &str) -> Result<Vec<String>, String> {
+    let mut names: Vec<String> = env_file::load_env_file(folder_path)?
+        .into_keys()
+        .chain(get_env_vars(conn, folder_path)?.into_keys())
+        .chain([DEFAULT_ENV_PROFILE.to_string()])
+        .collect();
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// The environment for a run: the default profile, overlaid by the named one.
+/// Within each profile, values stored in the database win over the file.
+pub fn resolve_env(
+    conn: &DbConnection,
+    folder_path: &str,
+    profile: Option<&str>,
+) -> Result<EnvVars, String> {
+    let file = env_file::load_env_file(folder_path)?;
+    let stored = get_env_vars(conn, folder_path)?;
+    merge_profiles(&file, &stored, profile)
+}
+
+fn merge_profiles(
+    file: &EnvProfiles,
+    stored: &EnvProfiles,
+    profile: Option<&str>,
+) -> Result<EnvVars, String> {
+    let mut names = vec![DEFAULT_ENV_PROFILE];
+    if let Some(name) = profile.filter(|name| *name != DEFAULT_ENV_PROFILE) {
+        if !file.contains_key(name) && !stored.contains_key(name) {
+            return Err(format!("Env profile '{}' is not defined", name));
+        }
+        names.push(name);
+    }
+
+    let mut env = EnvVars::new();
+    for name in names {
+        for source in [file, stored] {
+            if let Some(vars) = source.get(name) {
+                env.extend(vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+        }
+    }
+
+    Ok(env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_db;
+
+    #[test]
+    fn test_env_vars_round_trip() {
+        let conn = test_db();
+        set_env_var(&conn, "/work", "test", "DATABASE_URL", "sqlite://a").unwrap();
+        set_env_var(&conn, "/work", "test", "DATABASE_URL", "sqlite://b").unwrap();
+        set_env_var(&conn, "/work", "default", "RUST_LOG", "debug").unwrap();
+        assert!(set_env_var(&conn, "/work", "test", "NOT-VALID", "x").is_err());
+
+        let profiles = get_env_vars(&conn, "/work").unwrap();
+        assert_eq!(profiles["test"]["DATABASE_URL"], "sqlite://b");
+        assert_eq!(profiles["default"]["RUST_LOG"], "debug");
+
+        delete_env_var(&conn, "/work", "test", "DATABASE_URL").unwrap();
+        assert!(!get_env_vars(&conn, "/work").unwrap().contains_key("test"));
+    }
+
+    #[test]
+    fn test_merge_profiles_precedence() {
+        let file = env_file::parse_env_file(
+            "RUST_LOG=info\nSHARED=file\n[proxy]\nANTHROPIC_BASE_URL=http://file\n",
+        )
+        .unwrap();
+        let stored = EnvProfiles::from([
+            (
+                "default".to_string(),
+                EnvVars::from([("SHARED".to_string(), "db".to_string())]),
+            ),
+            (
+                "proxy".to_string(),
+                EnvVars::from([("RUST_LOG".to_string(), "trace".to_string())]),
+            ),
+        ]);
+
+        let env = merge_profiles(&file, &stored, None).unwrap();
+        assert_eq!(env["RUST_LOG"], "info");
+        assert_eq!(env["SHARED"], "db");
+        assert!(!env.contains_key("ANTHROPIC_BASE_URL"));
+
+        let env = merge_profiles(&file, &stored, Some("proxy")).unwrap();
+        assert_eq!(env["RUST_LOG"], "trace");
+        assert_eq!(env["ANTHROPIC_BASE_URL"], "http://file");
+
+        assert!(merge_profiles(&file, &stored, Some("missing")).is_err());
+    }
+}
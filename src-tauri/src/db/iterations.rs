@@ -59,6 +59,27 @@ pub fn update_ralph_iteration_session_id(
     Ok(())
 }
 
+/// Record the provider a Ralph iteration runs on, which may be a fallback
+/// rather than the folder's provider
+pub fn update_ralph_iteration_provider(
+    conn: &DbConnection,
+    folder_path: &str,
+    prd_name: &str,
+    iteration_number: i32,
+    provider: &str,
+) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    conn.execute(
+        "UPDATE ralph_iterations SET provider = ?1
+         WHERE folder_path = ?2 AND prd_name = ?3 AND iteration_number = ?4",
+        params![provider, folder_path, prd_name, iteration_number],
+    )
+    .map_err(|e| format!("Failed to update ralph iteration provider: {}", e))?;
+
+    Ok(())
+}
+
 /// Update Ralph iteration status
 pub fn update_ralph_iteration_status(
     conn: &DbConnection,
@@ -89,7 +110,8 @@ pub fn get_ralph_iterations(
 
     let mut stmt = conn
         .prepare(
-            "SELECT ri.id, ri.folder_path, ri.prd_name, ri.iteration_number, ri.session_id, ri.status, ri.created_at, s.provider
+            "SELECT ri.id, ri.folder_path, ri.prd_name, ri.iteration_number, ri.session_id, ri.status, ri.created_at,
+                    COALESCE(ri.provider, s.provider)
              FROM ralph_iterations ri
              LEFT JOIN sessions s ON ri.session_id = s.id
              WHERE ri.folder_path = ?1 AND ri.prd_name = ?2
@@ -126,7 +148,8 @@ pub fn get_all_ralph_iterations(
 
     let mut stmt = conn
         .prepare(
-            "SELECT ri.id, ri.folder_path, ri.prd_name, ri.iteration_number, ri.session_id, ri.status, ri.created_at, s.provider
+            "SELECT ri.id, ri.folder_path, ri.prd_name, ri.iteration_number, ri.session_id, ri.status, ri.created_at,
+                    COALESCE(ri.provider, s.provider)
              FROM ralph_iterations ri
              LEFT JOIN sessions s ON ri.session_id = s.id
              WHERE ri.folder_path = ?1
@@ -159,6 +182,44 @@ pub fn get_all_ralph_iterations(
     Ok(result)
 }
 
+/// The first iteration of the loop that `iteration_number` belongs to. The
+/// loop only carries on past iterations that finished or timed out, so the
+/// run starts after the latest earlier iteration that ended any other way.
+/// A loop restarted after the PRD completed counts on from the earlier one.
+/// `None` when the iteration hasn't been saved.
+pub fn get_run_start_iteration(
+    conn: &DbConnection,
+    folder_path: &str,
+    prd_name: &str,
+    iteration_number: i32,
+) -> Result<Option<i32>, String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    let saved: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM ralph_iterations
+             WHERE folder_path = ?1 AND prd_name = ?2 AND iteration_number = ?3)",
+            params![folder_path, prd_name, iteration_number],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to query ralph iteration: {}", e))?;
+    if !saved {
+        return Ok(None);
+    }
+
+    conn.query_row(
+        "SELECT COALESCE(
+             (SELECT MAX(iteration_number) FROM ralph_iterations
+              WHERE folder_path = ?1 AND prd_name = ?2 AND iteration_number < ?3
+                AND status NOT IN ('completed', 'timed_out')) + 1,
+             (SELECT MIN(iteration_number) FROM ralph_iterations
+              WHERE folder_path = ?1 AND prd_name = ?2))",
+        params![folder_path, prd_name, iteration_number],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to query run start iteration: {}", e))
+}
+
 /// Mark all running iterations as stopped (called on app startup)
 pub fn mark_running_iterations_stopped(conn: &DbConnection) -> Result<(), String> {
     let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
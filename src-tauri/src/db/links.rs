@@ -1,6 +1,6 @@
 use super::DbConnection;
 use chrono::Utc;
-use rusqlite::{params, OptionalExtension};
+use rusqlite::{params, OptionalExtension, Row};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,6 +58,22 @@ pub fn get_link_by_ralph_prd(
     get_link_by_file(conn, folder_path, prd_name, "ralph_prd")
 }
 
+const LINK_COLUMNS: &str = "sl.id, sl.folder_path, sl.session_id, sl.file_name, sl.link_type,
+    sl.created_at, sl.updated_at, s.provider";
+
+fn row_to_link(row: &Row) -> rusqlite::Result<SessionLink> {
+    Ok(SessionLink {
+        id: row.get(0)?,
+        folder_path: row.get(1)?,
+        session_id: row.get(2)?,
+        file_name: row.get(3)?,
+        link_type: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+        provider: row.get(7)?,
+    })
+}
+
 /// Get link by file name and type
 fn get_link_by_file(
     conn: &DbConnection,
@@ -68,33 +84,51 @@ fn get_link_by_file(
     let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
 
     let mut stmt = conn
-        .prepare(
-            "SELECT sl.id, sl.folder_path, sl.session_id, sl.file_name, sl.link_type, sl.created_at, sl.updated_at, s.provider
+        .prepare(&format!(
+            "SELECT {}
              FROM session_links sl
              JOIN sessions s ON sl.session_id = s.id
              WHERE sl.folder_path = ?1 AND sl.file_name = ?2 AND sl.link_type = ?3",
-        )
+            LINK_COLUMNS
+        ))
         .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
     let result = stmt
-        .query_row(params![folder_path, file_name, link_type], |row| {
-            Ok(SessionLink {
-                id: row.get(0)?,
-                folder_path: row.get(1)?,
-                session_id: row.get(2)?,
-                file_name: row.get(3)?,
-                link_type: row.get(4)?,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-                provider: row.get(7)?,
-            })
-        })
+        .query_row(params![folder_path, file_name, link_type], row_to_link)
         .optional()
         .map_err(|e| format!("Failed to get link: {}", e))?;
 
     Ok(result)
 }
 
+/// Get the plans and PRDs a session is linked to
+pub fn get_session_links(
+    conn: &DbConnection,
+    folder_path: &str,
+    session_id: &str,
+) -> Result<Vec<SessionLink>, String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {}
+             FROM session_links sl
+             JOIN sessions s ON sl.session_id = s.id
+             WHERE sl.folder_path = ?1 AND sl.session_id = ?2
+             ORDER BY sl.updated_at DESC",
+            LINK_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let links = stmt
+        .query_map(params![folder_path, session_id], row_to_link)
+        .map_err(|e| format!("Failed to query links: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read row: {}", e))?;
+
+    Ok(links)
+}
+
 /// Update plan link filename (for renames)
 pub fn update_plan_link_filename(
     conn: &DbConnection,
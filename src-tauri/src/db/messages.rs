@@ -1,6 +1,12 @@
 use super::DbConnection;
 use chrono::Utc;
 use rusqlite::params;
+use std::time::{Duration, Instant};
+
+/// Commit buffered messages once this many are pending
+const BATCH_MAX_MESSAGES: usize = 20;
+/// Commit buffered messages once the oldest has waited this long
+const BATCH_MAX_AGE: Duration = Duration::from_secs(2);
 
 /// Save a message to the database
 pub fn save_message(
@@ -66,3 +72,175 @@ pub fn get_next_sequence(conn: &DbConnection, session_id: &str) -> Result<i32, S
     Ok(max_seq.unwrap_or(0) + 1)
 }
 
+/// A message waiting to be written by a `MessageBatcher`
+struct PendingMessage {
+    message_type: String,
+    message_json: String,
+}
+
+/// Buffers a session's streamed messages and writes them in transactions.
+/// Commits whenever `BATCH_MAX_MESSAGES` are pending or the oldest pending
+/// message is older than `BATCH_MAX_AGE`, so a crash loses at most a few
+/// seconds of output. Sequence numbers are allocated when a batch is written,
+/// so two runs appending to the same session interleave instead of
+/// overwriting each other.
+pub struct MessageBatcher {
+    conn: DbConnection,
+    session_id: String,
+    pending: Vec<PendingMessage>,
+    oldest_pending: Option<Instant>,
+}
+
+impl MessageBatcher {
+    /// Start appending to a session after its existing messages
+    pub fn new(conn: &DbConnection, session_id: &str) -> Self {
+        Self {
+            conn: conn.clone(),
+            session_id: session_id.to_string(),
+            pending: Vec::new(),
+            oldest_pending: None,
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Queue a message, committing if the batch is full or due
+    pub fn push(&mut self, message_type: &str, message_json: String) -> Result<(), String> {
+        self.pending.push(PendingMessage {
+            message_type: message_type.to_string(),
+            message_json,
+        });
+        self.oldest_pending.get_or_insert_with(Instant::now);
+
+        if self.pending.len() >= BATCH_MAX_MESSAGES || self.is_due() {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Whether pending messages have waited long enough to be committed
+    pub fn is_due(&self) -> bool {
+        self.oldest_pending
+            .is_some_and(|since| since.elapsed() >= BATCH_MAX_AGE)
+    }
+
+    /// Write all pending messages in a single transaction, after the session's
+    /// last message. A sequence that's already taken fails the batch rather
+    /// than replacing the message.
+    pub fn flush(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+        let now = Utc::now().to_rfc3339();
+
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+        {
+            let max_sequence: Option<i32> = tx
+                .query_row(
+                    "SELECT MAX(sequence) FROM messages WHERE session_id = ?1",
+                    params![self.session_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| format!("Failed to get max sequence: {}", e))?;
+
+            let mut stmt = tx
+                .prepare(
+                    "INSERT INTO messages (session_id, sequence, message_type, message_json, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                )
+                .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+            for (sequence, message) in (max_sequence.unwrap_or(0) + 1..).zip(&self.pending) {
+                stmt.execute(params![
+                    self.session_id,
+                    sequence,
+                    message.message_type,
+                    message.message_json,
+                    now
+                ])
+                .map_err(|e| format!("Failed to save message: {}", e))?;
+            }
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit messages: {}", e))?;
+
+        self.pending.clear();
+        self.oldest_pending = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_db;
+
+    fn message_count(conn: &DbConnection) -> i64 {
+        conn.lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM messages", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_batcher_commits_full_batches() {
+        let conn = test_db();
+        let mut batcher = MessageBatcher::new(&conn, "s1");
+
+        for i in 0..BATCH_MAX_MESSAGES - 1 {
+            batcher
+                .push("assistant", format!(r#"{{"type":"assistant","n":{}}}"#, i))
+                .unwrap();
+        }
+        assert_eq!(message_count(&conn), 0);
+
+        batcher
+            .push("result", r#"{"type":"result"}"#.to_string())
+            .unwrap();
+        assert_eq!(message_count(&conn), BATCH_MAX_MESSAGES as i64);
+
+        batcher.push("assistant", "{}".to_string()).unwrap();
+        batcher.flush().unwrap();
+        assert_eq!(message_count(&conn), BATCH_MAX_MESSAGES as i64 + 1);
+    }
+
+    #[test]
+    fn test_batcher_appends_after_existing_messages() {
+        let conn = test_db();
+        save_message(&conn, "s1", r#"{"type":"user","content":"hi"}"#, 1, "user").unwrap();
+
+        let mut batcher = MessageBatcher::new(&conn, "s1");
+        batcher
+            .push("assistant", r#"{"type":"assistant"}"#.to_string())
+            .unwrap();
+        batcher.flush().unwrap();
+
+        let messages = get_session_messages(&conn, "s1").unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["type"], "user");
+        assert_eq!(messages[1]["type"], "assistant");
+        assert_eq!(get_next_sequence(&conn, "s1").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_concurrent_batchers_keep_every_message() {
+        let conn = test_db();
+        let mut first = MessageBatcher::new(&conn, "s1");
+        let mut second = MessageBatcher::new(&conn, "s1");
+
+        first.push("assistant", r#"{"n":1}"#.to_string()).unwrap();
+        second.push("assistant", r#"{"n":2}"#.to_string()).unwrap();
+        second.flush().unwrap();
+        first.push("assistant", r#"{"n":3}"#.to_string()).unwrap();
+        first.flush().unwrap();
+
+        let messages = get_session_messages(&conn, "s1").unwrap();
+        let order: Vec<i64> = messages.iter().map(|m| m["n"].as_i64().unwrap()).collect();
+        assert_eq!(order, vec![2, 1, 3]);
+    }
+}
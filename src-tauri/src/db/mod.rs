@@ -1,28 +1,32 @@
+pub mod attempts;
+pub mod budget;
+pub mod env_vars;
 pub mod iterations;
 pub mod links;
 pub mod messages;
 pub mod schema;
 pub mod sessions;
 pub mod settings;
+pub mod usage;
 
+use crate::utils::paths::trellico_home;
 use rusqlite::Connection;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 pub type DbConnection = Arc<Mutex<Connection>>;
 
+/// A fresh in-memory database with all migrations applied
+#[cfg(test)]
+pub(crate) fn test_db() -> DbConnection {
+    let conn = Connection::open_in_memory().unwrap();
+    schema::run_migrations(&conn).unwrap();
+    Arc::new(Mutex::new(conn))
+}
+
 /// Get the path to the trellico database file (~/.trellico/trellico.db)
 pub fn get_db_path() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("Cannot find home directory")?;
-    let trellico_dir = home.join(".trellico");
-
-    // Create directory if it doesn't exist
-    if !trellico_dir.exists() {
-        std::fs::create_dir_all(&trellico_dir)
-            .map_err(|e| format!("Failed to create .trellico directory: {}", e))?;
-    }
-
-    Ok(trellico_dir.join("trellico.db"))
+    Ok(trellico_home()?.join("trellico.db"))
 }
 
 /// Initialize the database connection and run migrations
@@ -1,8 +1,6 @@
 use chrono::Utc;
 use rusqlite::Connection;
 
-const CURRENT_VERSION: i32 = 1;
-
 /// Run database migrations
 pub fn run_migrations(conn: &Connection) -> Result<(), String> {
     // Create schema_version table if it doesn't exist
@@ -28,6 +26,33 @@ pub fn run_migrations(conn: &Connection) -> Result<(), String> {
     if current_version < 1 {
         migrate_v1(conn)?;
     }
+    if current_version < 2 {
+        migrate_v2(conn)?;
+    }
+    if current_version < 3 {
+        migrate_v3(conn)?;
+    }
+    if current_version < 4 {
+        migrate_v4(conn)?;
+    }
+    if current_version < 5 {
+        migrate_v5(conn)?;
+    }
+    if current_version < 6 {
+        migrate_v6(conn)?;
+    }
+    if current_version < 7 {
+        migrate_v7(conn)?;
+    }
+    if current_version < 8 {
+        migrate_v8(conn)?;
+    }
+    if current_version < 9 {
+        migrate_v9(conn)?;
+    }
+    if current_version < 10 {
+        migrate_v10(conn)?;
+    }
 
     Ok(())
 }
@@ -121,13 +146,249 @@ fn migrate_v1(conn: &Connection) -> Result<(), String> {
     )
     .map_err(|e| format!("Failed to create folder_settings table: {}", e))?;
 
-    // Record migration
+    record_migration(conn, 1)
+}
+
+/// Version 2: Global app settings (key/value)
+fn migrate_v2(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create app_settings table: {}", e))?;
+
+    record_migration(conn, 2)
+}
+
+/// Version 3: Token usage and cost per provider run
+fn migrate_v3(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_records (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id TEXT,
+            process_id TEXT NOT NULL,
+            folder_path TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            ralph_prd TEXT,
+            ralph_iteration INTEGER,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_creation_input_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_read_input_tokens INTEGER NOT NULL DEFAULT 0,
+            cost_usd REAL,
+            recorded_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create usage_records table: {}", e))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_usage_records_session_id ON usage_records(session_id)",
+        [],
+    )
+    .map_err(|e| format!("Failed to create usage_records session index: {}", e))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_usage_records_folder ON usage_records(folder_path, recorded_at)",
+        [],
+    )
+    .map_err(|e| format!("Failed to create usage_records folder index: {}", e))?;
+
+    record_migration(conn, 3)
+}
+
+/// Version 4: Per-folder budgets (NULL = no limit)
+fn migrate_v4(conn: &Connection) -> Result<(), String> {
+    in_transaction(conn, |conn| {
+        for column in [
+            "max_daily_cost_usd REAL",
+            "max_tokens_per_prd INTEGER",
+            "max_iterations_per_run INTEGER",
+        ] {
+            conn.execute(
+                &format!("ALTER TABLE folder_settings ADD COLUMN {}", column),
+                [],
+            )
+            .map_err(|e| format!("Failed to add folder_settings column: {}", e))?;
+        }
+
+        record_migration(conn, 4)
+    })
+}
+
+/// Version 5: Model per folder, and the model each session ran with
+fn migrate_v5(conn: &Connection) -> Result<(), String> {
+    in_transaction(conn, |conn| {
+        conn.execute("ALTER TABLE folder_settings ADD COLUMN model TEXT", [])
+            .map_err(|e| format!("Failed to add folder_settings.model: {}", e))?;
+        conn.execute("ALTER TABLE sessions ADD COLUMN model TEXT", [])
+            .map_err(|e| format!("Failed to add sessions.model: {}", e))?;
+
+        record_migration(conn, 5)
+    })
+}
+
+/// Version 6: Permission profiles per folder, per Ralph PRD and per session
+fn migrate_v6(conn: &Connection) -> Result<(), String> {
+    in_transaction(conn, |conn| {
+        conn.execute(
+            "ALTER TABLE folder_settings ADD COLUMN permission_profile TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add folder_settings.permission_profile: {}", e))?;
+        conn.execute(
+            "ALTER TABLE sessions ADD COLUMN permission_profile TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add sessions.permission_profile: {}", e))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS prd_settings (
+                folder_path TEXT NOT NULL,
+                prd_name TEXT NOT NULL,
+                permission_profile TEXT,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY(folder_path, prd_name)
+            )",
+            [],
+        )
+        .map_err(|e| format!("Failed to create prd_settings table: {}", e))?;
+
+        record_migration(conn, 6)
+    })
+}
+
+/// Version 7: Environment profiles per folder
+fn migrate_v7(conn: &Connection) -> Result<(), String> {
+    in_transaction(conn, |conn| {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS folder_env_vars (
+                folder_path TEXT NOT NULL,
+                profile TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY(folder_path, profile, key)
+            )",
+            [],
+        )
+        .map_err(|e| format!("Failed to create folder_env_vars table: {}", e))?;
+
+        conn.execute(
+            "ALTER TABLE folder_settings ADD COLUMN env_profile TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add folder_settings.env_profile: {}", e))?;
+
+        record_migration(conn, 7)
+    })
+}
+
+/// Version 8: Concurrent run limit per folder
+fn migrate_v8(conn: &Connection) -> Result<(), String> {
+    in_transaction(conn, |conn| {
+        conn.execute(
+            "ALTER TABLE folder_settings ADD COLUMN max_concurrent_runs INTEGER",
+            [],
+        )
+        .map_err(|e| format!("Failed to add folder_settings.max_concurrent_runs: {}", e))?;
+
+        record_migration(conn, 8)
+    })
+}
+
+/// Version 9: Every attempt of a run, including automatic retries
+fn migrate_v9(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS run_attempts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            process_id TEXT NOT NULL,
+            attempt INTEGER NOT NULL,
+            folder_path TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            session_id TEXT,
+            ralph_prd TEXT,
+            ralph_iteration INTEGER,
+            outcome TEXT NOT NULL,
+            exit_code INTEGER,
+            error TEXT,
+            started_at TEXT NOT NULL,
+            ended_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create run_attempts table: {}", e))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_run_attempts_iteration ON run_attempts(folder_path, ralph_prd, ralph_iteration)",
+        [],
+    )
+    .map_err(|e| format!("Failed to create run_attempts iteration index: {}", e))?;
+
+    record_migration(conn, 9)
+}
+
+/// Version 10: Fallback providers per folder, and the provider each Ralph iteration ran on
+fn migrate_v10(conn: &Connection) -> Result<(), String> {
+    in_transaction(conn, |conn| {
+        conn.execute(
+            "ALTER TABLE folder_settings ADD COLUMN fallback_providers TEXT",
+            [],
+        )
+        .map_err(|e| format!("Failed to add folder_settings.fallback_providers: {}", e))?;
+        conn.execute("ALTER TABLE ralph_iterations ADD COLUMN provider TEXT", [])
+            .map_err(|e| format!("Failed to add ralph_iterations.provider: {}", e))?;
+
+        record_migration(conn, 10)
+    })
+}
+
+/// Run a migration and its `record_migration` as one transaction. `ADD COLUMN`
+/// can't be repeated, so a migration cut short must leave nothing behind.
+fn in_transaction(
+    conn: &Connection,
+    migrate: impl FnOnce(&Connection) -> Result<(), String>,
+) -> Result<(), String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start migration: {}", e))?;
+    migrate(&tx)?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit migration: {}", e))
+}
+
+fn record_migration(conn: &Connection, version: i32) -> Result<(), String> {
     let now = Utc::now().to_rfc3339();
     conn.execute(
         "INSERT INTO schema_version (version, applied_at) VALUES (?, ?)",
-        [&CURRENT_VERSION.to_string(), &now],
+        [&version.to_string(), &now],
     )
     .map_err(|e| format!("Failed to record migration: {}", e))?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interrupted_migration_rolls_back() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE t (a TEXT)", []).unwrap();
+
+        let result = in_transaction(&conn, |conn| {
+            conn.execute("ALTER TABLE t ADD COLUMN b TEXT", [])
+                .map_err(|e| e.to_string())?;
+            Err("interrupted".to_string())
+        });
+        assert!(result.is_err());
+
+        // Nothing was left behind, so the migration can run again
+        conn.execute("ALTER TABLE t ADD COLUMN b TEXT", []).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 use super::DbConnection;
+use crate::providers::PermissionProfile;
 use chrono::Utc;
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
@@ -7,6 +8,8 @@ use serde::{Deserialize, Serialize};
 pub struct FolderSession {
     pub id: String,
     pub provider: String,
+    pub model: Option<String>,
+    pub permission_profile: Option<PermissionProfile>,
     pub session_type: String,
     pub display_name: Option<String>,
     pub created_at: String,
@@ -46,7 +49,8 @@ pub fn get_folder_sessions(
     let mut stmt = conn
         .prepare(
             "SELECT s.id, s.provider, s.session_type, s.display_name, s.created_at,
-                    sl_plan.file_name, sl_ralph.file_name, ri.prd_name
+                    sl_plan.file_name, sl_ralph.file_name, ri.prd_name, s.model,
+                    s.permission_profile
              FROM sessions s
              LEFT JOIN session_links sl_plan ON s.id = sl_plan.session_id AND sl_plan.link_type = 'plan'
              LEFT JOIN session_links sl_ralph ON s.id = sl_ralph.session_id AND sl_ralph.link_type = 'ralph_prd'
@@ -67,6 +71,10 @@ pub fn get_folder_sessions(
                 linked_plan: row.get(5)?,
                 linked_ralph_prd: row.get(6)?,
                 linked_ralph_iteration: row.get(7)?,
+                model: row.get(8)?,
+                permission_profile: row
+                    .get::<_, Option<String>>(9)?
+                    .and_then(|json| PermissionProfile::from_json(&json).ok()),
             })
         })
         .map_err(|e| format!("Failed to query sessions: {}", e))?
@@ -76,6 +84,38 @@ pub fn get_folder_sessions(
     Ok(sessions)
 }
 
+/// Record the model a session last ran with
+pub fn set_session_model(conn: &DbConnection, session_id: &str, model: &str) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let now = Utc::now().to_rfc3339();
+
+    conn.execute(
+        "UPDATE sessions SET model = ?1, updated_at = ?2 WHERE id = ?3",
+        params![model, now, session_id],
+    )
+    .map_err(|e| format!("Failed to update session model: {}", e))?;
+
+    Ok(())
+}
+
+/// Record the permission profile a session last ran with
+pub fn set_session_permission_profile(
+    conn: &DbConnection,
+    session_id: &str,
+    profile: &PermissionProfile,
+) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let now = Utc::now().to_rfc3339();
+
+    conn.execute(
+        "UPDATE sessions SET permission_profile = ?1, updated_at = ?2 WHERE id = ?3",
+        params![profile.to_json(), now, session_id],
+    )
+    .map_err(|e| format!("Failed to update session permission profile: {}", e))?;
+
+    Ok(())
+}
+
 /// Update session display name
 pub fn update_session_display_name(
     conn: &DbConnection,
@@ -1,7 +1,23 @@
 use super::DbConnection;
-use crate::providers::Provider;
+use crate::providers::{PermissionProfile, Provider, RunnerMode};
+use crate::utils::process_group::StopGrace;
+use crate::utils::retry::{self, RetryPolicy};
+use crate::utils::run_log::LogRetention;
 use chrono::Utc;
 use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Spending guardrails for a folder's Ralph loops; `None` means no limit
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FolderBudget {
+    /// Dollars per local calendar day, across all runs in the folder
+    pub max_daily_cost_usd: Option<f64>,
+    /// Tokens (input, output and cache) across all iterations of a PRD
+    pub max_tokens_per_prd: Option<i64>,
+    /// Iterations in one Ralph loop
+    pub max_iterations_per_run: Option<i32>,
+}
 
 /// Get the provider for a folder
 pub fn get_folder_provider(conn: &DbConnection, folder_path: &str) -> Result<Provider, String> {
@@ -17,11 +33,8 @@ pub fn get_folder_provider(conn: &DbConnection, folder_path: &str) -> Result<Pro
         .map_err(|e| format!("Failed to get folder provider: {}", e))?;
 
     match result {
-        Some(provider_str) => {
-            // Parse the provider string
-            let provider_json = format!("\"{}\"", provider_str);
-            serde_json::from_str(&provider_json).map_err(|e| format!("Invalid provider: {}", e))
-        }
+        // Resolve the stored id through the provider registry
+        Some(provider_str) => Provider::from_id(&provider_str),
         None => Ok(Provider::default()),
     }
 }
@@ -34,20 +47,577 @@ pub fn set_folder_provider(
 ) -> Result<(), String> {
     let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
     let now = Utc::now().to_rfc3339();
-    let provider_str = serde_json::to_string(&provider)
-        .map_err(|e| format!("Serialize error: {}", e))?
-        .trim_matches('"')
-        .to_string();
-
     conn.execute(
         "INSERT INTO folder_settings (folder_path, provider, updated_at)
          VALUES (?1, ?2, ?3)
          ON CONFLICT(folder_path) DO UPDATE SET
             provider = excluded.provider,
             updated_at = excluded.updated_at",
-        params![folder_path, provider_str, now],
+        params![folder_path, provider.id(), now],
     )
     .map_err(|e| format!("Failed to set folder provider: {}", e))?;
 
     Ok(())
 }
+
+/// Get the model for a folder (`None` uses the provider's default)
+pub fn get_folder_model(conn: &DbConnection, folder_path: &str) -> Result<Option<String>, String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    let result: Option<Option<String>> = conn
+        .query_row(
+            "SELECT model FROM folder_settings WHERE folder_path = ?1",
+            params![folder_path],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to get folder model: {}", e))?;
+
+    Ok(result.flatten())
+}
+
+/// Set the model for a folder
+pub fn set_folder_model(
+    conn: &DbConnection,
+    folder_path: &str,
+    model: Option<&str>,
+) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO folder_settings (folder_path, model, updated_at)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(folder_path) DO UPDATE SET
+            model = excluded.model,
+            updated_at = excluded.updated_at",
+        params![folder_path, model, now],
+    )
+    .map_err(|e| format!("Failed to set folder model: {}", e))?;
+
+    Ok(())
+}
+
+/// Get the env profile a folder's runs use (`None` is only the default profile)
+pub fn get_folder_env_profile(
+    conn: &DbConnection,
+    folder_path: &str,
+) -> Result<Option<String>, String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    let result: Option<Option<String>> = conn
+        .query_row(
+            "SELECT env_profile FROM folder_settings WHERE folder_path = ?1",
+            params![folder_path],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to get folder env profile: {}", e))?;
+
+    Ok(result.flatten())
+}
+
+/// Set the env profile for a folder
+pub fn set_folder_env_profile(
+    conn: &DbConnection,
+    folder_path: &str,
+    profile: Option<&str>,
+) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO folder_settings (folder_path, env_profile, updated_at)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(folder_path) DO UPDATE SET
+            env_profile = excluded.env_profile,
+            updated_at = excluded.updated_at",
+        params![folder_path, profile, now],
+    )
+    .map_err(|e| format!("Failed to set folder env profile: {}", e))?;
+
+    Ok(())
+}
+
+/// Get the permission profile for a folder, if one was chosen
+pub fn get_folder_permission_profile(
+    conn: &DbConnection,
+    folder_path: &str,
+) -> Result<Option<PermissionProfile>, String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    let result: Option<Option<String>> = conn
+        .query_row(
+            "SELECT permission_profile FROM folder_settings WHERE folder_path = ?1",
+            params![folder_path],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to get folder permission profile: {}", e))?;
+
+    result
+        .flatten()
+        .map(|json| PermissionProfile::from_json(&json))
+        .transpose()
+}
+
+/// Set the permission profile for a folder (`None` clears it)
+pub fn set_folder_permission_profile(
+    conn: &DbConnection,
+    folder_path: &str,
+    profile: Option<&PermissionProfile>,
+) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO folder_settings (folder_path, permission_profile, updated_at)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(folder_path) DO UPDATE SET
+            permission_profile = excluded.permission_profile,
+            updated_at = excluded.updated_at",
+        params![folder_path, profile.map(PermissionProfile::to_json), now],
+    )
+    .map_err(|e| format!("Failed to set folder permission profile: {}", e))?;
+
+    Ok(())
+}
+
+/// Get the permission profile for a Ralph PRD, if one was chosen
+pub fn get_prd_permission_profile(
+    conn: &DbConnection,
+    folder_path: &str,
+    prd_name: &str,
+) -> Result<Option<PermissionProfile>, String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    let result: Option<Option<String>> = conn
+        .query_row(
+            "SELECT permission_profile FROM prd_settings WHERE folder_path = ?1 AND prd_name = ?2",
+            params![folder_path, prd_name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to get PRD permission profile: {}", e))?;
+
+    result
+        .flatten()
+        .map(|json| PermissionProfile::from_json(&json))
+        .transpose()
+}
+
+/// Set the permission profile for a Ralph PRD (`None` falls back to the folder's)
+pub fn set_prd_permission_profile(
+    conn: &DbConnection,
+    folder_path: &str,
+    prd_name: &str,
+    profile: Option<&PermissionProfile>,
+) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO prd_settings (folder_path, prd_name, permission_profile, updated_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(folder_path, prd_name) DO UPDATE SET
+            permission_profile = excluded.permission_profile,
+            updated_at = excluded.updated_at",
+        params![
+            folder_path,
+            prd_name,
+            profile.map(PermissionProfile::to_json),
+            now
+        ],
+    )
+    .map_err(|e| format!("Failed to set PRD permission profile: {}", e))?;
+
+    Ok(())
+}
+
+/// The permission profile a run uses: the PRD's, then the folder's, then full
+pub fn resolve_permission_profile(
+    conn: &DbConnection,
+    folder_path: &str,
+    prd_name: Option<&str>,
+) -> Result<PermissionProfile, String> {
+    if let Some(prd_name) = prd_name {
+        if let Some(profile) = get_prd_permission_profile(conn, folder_path, prd_name)? {
+            return Ok(profile);
+        }
+    }
+    Ok(get_folder_permission_profile(conn, folder_path)?.unwrap_or_default())
+}
+
+/// Get how many runs a folder may have going at once (`None` is only the global limit)
+pub fn get_folder_max_concurrent_runs(
+    conn: &DbConnection,
+    folder_path: &str,
+) -> Result<Option<usize>, String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    let result: Option<Option<i64>> = conn
+        .query_row(
+            "SELECT max_concurrent_runs FROM folder_settings WHERE folder_path = ?1",
+            params![folder_path],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to get folder concurrency limit: {}", e))?;
+
+    Ok(result.flatten().filter(|n| *n > 0).map(|n| n as usize))
+}
+
+/// Set how many runs a folder may have going at once
+pub fn set_folder_max_concurrent_runs(
+    conn: &DbConnection,
+    folder_path: &str,
+    limit: Option<usize>,
+) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO folder_settings (folder_path, max_concurrent_runs, updated_at)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(folder_path) DO UPDATE SET
+            max_concurrent_runs = excluded.max_concurrent_runs,
+            updated_at = excluded.updated_at",
+        params![folder_path, limit.map(|n| n as i64), now],
+    )
+    .map_err(|e| format!("Failed to set folder concurrency limit: {}", e))?;
+
+    Ok(())
+}
+
+/// Get the providers a folder's Ralph loops fall back to, in order, as ids
+pub fn get_folder_fallback_providers(
+    conn: &DbConnection,
+    folder_path: &str,
+) -> Result<Vec<String>, String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    let result: Option<Option<String>> = conn
+        .query_row(
+            "SELECT fallback_providers FROM folder_settings WHERE folder_path = ?1",
+            params![folder_path],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to get folder fallback providers: {}", e))?;
+
+    match result.flatten() {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse folder fallback providers: {}", e)),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Set the providers a folder's Ralph loops fall back to, in order
+pub fn set_folder_fallback_providers(
+    conn: &DbConnection,
+    folder_path: &str,
+    providers: &[Provider],
+) -> Result<(), String> {
+    let ids: Vec<&str> = providers.iter().map(|p| p.id()).collect();
+    let json = serde_json::to_string(&ids)
+        .map_err(|e| format!("Failed to serialize fallback providers: {}", e))?;
+
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO folder_settings (folder_path, fallback_providers, updated_at)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(folder_path) DO UPDATE SET
+            fallback_providers = excluded.fallback_providers,
+            updated_at = excluded.updated_at",
+        params![folder_path, json, now],
+    )
+    .map_err(|e| format!("Failed to set folder fallback providers: {}", e))?;
+
+    Ok(())
+}
+
+/// Get the budget for a folder
+pub fn get_folder_budget(conn: &DbConnection, folder_path: &str) -> Result<FolderBudget, String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    let result = conn
+        .query_row(
+            "SELECT max_daily_cost_usd, max_tokens_per_prd, max_iterations_per_run
+             FROM folder_settings WHERE folder_path = ?1",
+            params![folder_path],
+            |row| {
+                Ok(FolderBudget {
+                    max_daily_cost_usd: row.get(0)?,
+                    max_tokens_per_prd: row.get(1)?,
+                    max_iterations_per_run: row.get(2)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to get folder budget: {}", e))?;
+
+    Ok(result.unwrap_or_default())
+}
+
+/// Set the budget for a folder
+pub fn set_folder_budget(
+    conn: &DbConnection,
+    folder_path: &str,
+    budget: &FolderBudget,
+) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO folder_settings (folder_path, max_daily_cost_usd, max_tokens_per_prd, max_iterations_per_run, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(folder_path) DO UPDATE SET
+            max_daily_cost_usd = excluded.max_daily_cost_usd,
+            max_tokens_per_prd = excluded.max_tokens_per_prd,
+            max_iterations_per_run = excluded.max_iterations_per_run,
+            updated_at = excluded.updated_at",
+        params![
+            folder_path,
+            budget.max_daily_cost_usd,
+            budget.max_tokens_per_prd,
+            budget.max_iterations_per_run,
+            now
+        ],
+    )
+    .map_err(|e| format!("Failed to set folder budget: {}", e))?;
+
+    Ok(())
+}
+
+/// Get a global app setting
+pub fn get_app_setting(conn: &DbConnection, key: &str) -> Result<Option<String>, String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to get app setting: {}", e))
+}
+
+/// Set a global app setting
+pub fn set_app_setting(conn: &DbConnection, key: &str, value: &str) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO app_settings (key, value, updated_at)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(key) DO UPDATE SET
+            value = excluded.value,
+            updated_at = excluded.updated_at",
+        params![key, value, now],
+    )
+    .map_err(|e| format!("Failed to set app setting: {}", e))?;
+
+    Ok(())
+}
+
+/// Remove a global app setting, restoring its default
+pub fn delete_app_setting(conn: &DbConnection, key: &str) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    conn.execute("DELETE FROM app_settings WHERE key = ?1", params![key])
+        .map_err(|e| format!("Failed to delete app setting: {}", e))?;
+
+    Ok(())
+}
+
+fn binary_path_key(provider_id: &str) -> String {
+    format!("binary_path.{}", provider_id)
+}
+
+/// Get the binary path override for a provider
+pub fn get_binary_path(conn: &DbConnection, provider_id: &str) -> Result<Option<String>, String> {
+    get_app_setting(conn, &binary_path_key(provider_id))
+}
+
+/// Set (or with `None`, clear) the binary path override for a provider
+pub fn set_binary_path(
+    conn: &DbConnection,
+    provider_id: &str,
+    path: Option<&str>,
+) -> Result<(), String> {
+    match path {
+        Some(path) => set_app_setting(conn, &binary_path_key(provider_id), path),
+        None => delete_app_setting(conn, &binary_path_key(provider_id)),
+    }
+}
+
+fn runner_mode_key(provider_id: &str) -> String {
+    format!("runner_mode.{}", provider_id)
+}
+
+/// Get the runner chosen for a provider; unset or invalid values are `None`
+pub fn get_runner_mode(
+    conn: &DbConnection,
+    provider_id: &str,
+) -> Result<Option<RunnerMode>, String> {
+    Ok(get_app_setting(conn, &runner_mode_key(provider_id))?
+        .and_then(|name| RunnerMode::from_name(&name)))
+}
+
+/// Set (or with `None`, clear) the runner chosen for a provider
+pub fn set_runner_mode(
+    conn: &DbConnection,
+    provider_id: &str,
+    mode: Option<RunnerMode>,
+) -> Result<(), String> {
+    match mode {
+        Some(mode) => set_app_setting(conn, &runner_mode_key(provider_id), mode.name()),
+        None => delete_app_setting(conn, &runner_mode_key(provider_id)),
+    }
+}
+
+/// Get a numeric global app setting; unset or invalid values are `None`
+pub fn get_app_setting_u64(conn: &DbConnection, key: &str) -> Result<Option<u64>, String> {
+    Ok(get_app_setting(conn, key)?.and_then(|v| v.trim().parse().ok()))
+}
+
+/// Grace periods used when stopping a run (`stop_interrupt_grace_ms` and
+/// `stop_terminate_grace_ms`); unset or invalid values use the defaults
+pub fn get_stop_grace(conn: &DbConnection) -> Result<StopGrace, String> {
+    let millis = |key: &str| -> Result<Option<Duration>, String> {
+        Ok(get_app_setting_u64(conn, key)?.map(Duration::from_millis))
+    };
+
+    let default = StopGrace::default();
+    Ok(StopGrace {
+        interrupt: millis("stop_interrupt_grace_ms")?.unwrap_or(default.interrupt),
+        terminate: millis("stop_terminate_grace_ms")?.unwrap_or(default.terminate),
+    })
+}
+
+/// How transient provider failures are retried, from the `max_retries`,
+/// `retry_base_delay_secs` and `retry_max_delay_secs` app settings. Delays
+/// are capped at `retry::MAX_DELAY`.
+pub fn get_retry_policy(conn: &DbConnection) -> Result<RetryPolicy, String> {
+    let secs = |key: &str| -> Result<Option<Duration>, String> {
+        Ok(get_app_setting_u64(conn, key)?
+            .map(|secs| Duration::from_secs(secs).min(retry::MAX_DELAY)))
+    };
+
+    let default = RetryPolicy::default();
+    Ok(RetryPolicy {
+        max_retries: get_app_setting_u64(conn, "max_retries")?.map_or(default.max_retries, |n| {
+            u32::try_from(n).unwrap_or(u32::MAX)
+        }),
+        base_delay: secs("retry_base_delay_secs")?.unwrap_or(default.base_delay),
+        max_delay: secs("retry_max_delay_secs")?.unwrap_or(default.max_delay),
+    })
+}
+
+/// How long raw run logs are kept, from the `log_retention_days` and
+/// `log_max_total_mb` app settings; 0 lifts a limit
+pub fn get_log_retention(conn: &DbConnection) -> Result<LogRetention, String> {
+    let limit = |key: &str, default: Option<u64>, unit: u64| -> Result<Option<u64>, String> {
+        Ok(match get_app_setting_u64(conn, key)? {
+            Some(0) => None,
+            Some(value) => Some(value.saturating_mul(unit)),
+            None => default,
+        })
+    };
+
+    let default = LogRetention::default();
+    Ok(LogRetention {
+        max_age: limit(
+            "log_retention_days",
+            default.max_age.map(|age| age.as_secs()),
+            24 * 60 * 60,
+        )?
+        .map(Duration::from_secs),
+        max_total_bytes: limit("log_max_total_mb", default.max_total_bytes, 1024 * 1024)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_db;
+
+    #[test]
+    fn test_permission_profile_resolution() {
+        let conn = test_db();
+
+        assert_eq!(
+            resolve_permission_profile(&conn, "/work", Some("prd")).unwrap(),
+            PermissionProfile::Full
+        );
+
+        set_folder_permission_profile(&conn, "/work", Some(&PermissionProfile::NoNetworkTools))
+            .unwrap();
+        assert_eq!(
+            resolve_permission_profile(&conn, "/work", Some("prd")).unwrap(),
+            PermissionProfile::NoNetworkTools
+        );
+
+        set_prd_permission_profile(&conn, "/work", "prd", Some(&PermissionProfile::ReadOnly))
+            .unwrap();
+        assert_eq!(
+            resolve_permission_profile(&conn, "/work", Some("prd")).unwrap(),
+            PermissionProfile::ReadOnly
+        );
+        assert_eq!(
+            resolve_permission_profile(&conn, "/work", None).unwrap(),
+            PermissionProfile::NoNetworkTools
+        );
+
+        set_prd_permission_profile(&conn, "/work", "prd", None).unwrap();
+        assert_eq!(
+            resolve_permission_profile(&conn, "/work", Some("prd")).unwrap(),
+            PermissionProfile::NoNetworkTools
+        );
+    }
+
+    #[test]
+    fn test_fallback_providers_round_trip() {
+        let conn = test_db();
+
+        assert!(get_folder_fallback_providers(&conn, "/work")
+            .unwrap()
+            .is_empty());
+
+        let providers = [
+            Provider::from_id("amp").unwrap(),
+            Provider::from_id("codex").unwrap(),
+        ];
+        set_folder_fallback_providers(&conn, "/work", &providers).unwrap();
+        assert_eq!(
+            get_folder_fallback_providers(&conn, "/work").unwrap(),
+            vec!["amp", "codex"]
+        );
+
+        set_folder_fallback_providers(&conn, "/work", &[]).unwrap();
+        assert!(get_folder_fallback_providers(&conn, "/work")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_retry_delays_capped() {
+        let conn = test_db();
+        set_app_setting(&conn, "retry_base_delay_secs", &u64::MAX.to_string()).unwrap();
+        set_app_setting(&conn, "retry_max_delay_secs", &u64::MAX.to_string()).unwrap();
+
+        let policy = get_retry_policy(&conn).unwrap();
+        assert_eq!(policy.base_delay, retry::MAX_DELAY);
+        assert_eq!(policy.delay(u32::MAX), retry::MAX_DELAY);
+    }
+
+    #[test]
+    fn test_log_retention() {
+        let conn = test_db();
+
+        assert_eq!(get_log_retention(&conn).unwrap(), LogRetention::default());
+
+        set_app_setting(&conn, "log_retention_days", "7").unwrap();
+        set_app_setting(&conn, "log_max_total_mb", "0").unwrap();
+        assert_eq!(
+            get_log_retention(&conn).unwrap(),
+            LogRetention {
+                max_age: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+                max_total_bytes: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use super::DbConnection;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use rusqlite::{params, params_from_iter, ToSql};
+use serde::{Deserialize, Serialize};
+
+/// Token usage and cost reported by one provider run
+#[derive(Debug, Clone, Default)]
+pub struct UsageRecord {
+    pub session_id: Option<String>,
+    pub process_id: String,
+    pub folder_path: String,
+    pub provider: String,
+    pub ralph_prd: Option<String>,
+    pub ralph_iteration: Option<i32>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    pub cost_usd: Option<f64>,
+}
+
+/// Summed usage over a set of runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct UsageTotals {
+    pub runs: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_input_tokens: i64,
+    pub cache_read_input_tokens: i64,
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    pub fn total_tokens(&self) -> i64 {
+        self.input_tokens
+            + self.output_tokens
+            + self.cache_creation_input_tokens
+            + self.cache_read_input_tokens
+    }
+}
+
+/// Record the usage of a run
+pub fn record_usage(conn: &DbConnection, record: &UsageRecord) -> Result<(), String> {
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let now = Utc::now().to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO usage_records (session_id, process_id, folder_path, provider, ralph_prd, ralph_iteration,
+            input_tokens, output_tokens, cache_creation_input_tokens, cache_read_input_tokens, cost_usd, recorded_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            record.session_id,
+            record.process_id,
+            record.folder_path,
+            record.provider,
+            record.ralph_prd,
+            record.ralph_iteration,
+            record.input_tokens as i64,
+            record.output_tokens as i64,
+            record.cache_creation_input_tokens as i64,
+            record.cache_read_input_tokens as i64,
+            record.cost_usd,
+            now
+        ],
+    )
+    .map_err(|e| format!("Failed to record usage: {}", e))?;
+
+    Ok(())
+}
+
+/// Optional bounds on `recorded_at`: `from` is inclusive, `to` exclusive.
+/// Held in UTC and formatted like `recorded_at`, so they compare as text.
+#[derive(Debug, Clone, Default)]
+pub struct DateRange {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+impl DateRange {
+    /// Bounds given as RFC 3339 timestamps in any offset, or `YYYY-MM-DD`
+    /// dates (UTC midnight)
+    pub fn parse(from: Option<&str>, to: Option<&str>) -> Result<Self, String> {
+        Ok(Self {
+            from: from.map(parse_bound).transpose()?,
+            to: to.map(parse_bound).transpose()?,
+        })
+    }
+
+    /// Everything recorded at or after `from`
+    pub fn since(from: DateTime<Utc>) -> Self {
+        Self {
+            from: Some(from.to_rfc3339()),
+            to: None,
+        }
+    }
+}
+
+fn parse_bound(bound: &str) -> Result<String, String> {
+    let at = match DateTime::parse_from_rfc3339(bound) {
+        Ok(at) => at.with_timezone(&Utc),
+        Err(_) => NaiveDate::parse_from_str(bound, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid date: {}", bound))?
+            .and_time(NaiveTime::MIN)
+            .and_utc(),
+    };
+    Ok(at.to_rfc3339())
+}
+
+/// Sum usage rows matching `filter` (an SQL condition over `params`) within `range`
+fn sum_usage(
+    conn: &DbConnection,
+    filter: &str,
+    params: &[&dyn ToSql],
+    range: &DateRange,
+) -> Result<UsageTotals, String> {
+    let mut params = params.to_vec();
+    let conn = conn.lock().map_err(|e| format!("Lock error: {}", e))?;
+
+    let mut sql = format!(
+        "SELECT COUNT(*), COALESCE(SUM(input_tokens), 0), COALESCE(SUM(output_tokens), 0),
+                COALESCE(SUM(cache_creation_input_tokens), 0), COALESCE(SUM(cache_read_input_tokens), 0),
+                COALESCE(SUM(cost_usd), 0)
+         FROM usage_records WHERE {}",
+        filter
+    );
+    if let Some(from) = &range.from {
+        params.push(from);
+        sql.push_str(&format!(" AND recorded_at >= ?{}", params.len()));
+    }
+    if let Some(to) = &range.to {
+        params.push(to);
+        sql.push_str(&format!(" AND recorded_at < ?{}", params.len()));
+    }
+
+    conn.query_row(&sql, params_from_iter(params), |row| {
+        Ok(UsageTotals {
+            runs: row.get(0)?,
+            input_tokens: row.get(1)?,
+            output_tokens: row.get(2)?,
+            cache_creation_input_tokens: row.get(3)?,
+            cache_read_input_tokens: row.get(4)?,
+            cost_usd: row.get(5)?,
+        })
+    })
+    .map_err(|e| format!("Failed to sum usage: {}", e))
+}
+
+/// Get total usage for a session
+pub fn get_session_usage(
+    conn: &DbConnection,
+    session_id: &str,
+    range: &DateRange,
+) -> Result<UsageTotals, String> {
+    sum_usage(conn, "session_id = ?1", &[&session_id], range)
+}
+
+/// Get total usage for a Ralph iteration
+pub fn get_iteration_usage(
+    conn: &DbConnection,
+    folder_path: &str,
+    prd_name: &str,
+    iteration_number: i32,
+    range: &DateRange,
+) -> Result<UsageTotals, String> {
+    sum_usage(
+        conn,
+        "folder_path = ?1 AND ralph_prd = ?2 AND ralph_iteration = ?3",
+        &[&folder_path, &prd_name, &iteration_number],
+        range,
+    )
+}
+
+/// Get total usage for all iterations of a Ralph PRD
+pub fn get_prd_usage(
+    conn: &DbConnection,
+    folder_path: &str,
+    prd_name: &str,
+    range: &DateRange,
+) -> Result<UsageTotals, String> {
+    sum_usage(
+        conn,
+        "folder_path = ?1 AND ralph_prd = ?2",
+        &[&folder_path, &prd_name],
+        range,
+    )
+}
+
+/// Get total usage for a folder
+pub fn get_folder_usage(
+    conn: &DbConnection,
+    folder_path: &str,
+    range: &DateRange,
+) -> Result<UsageTotals, String> {
+    sum_usage(conn, "folder_path = ?1", &[&folder_path], range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_db;
+
+    fn record(session: &str, prd: Option<&str>, iteration: Option<i32>, cost: f64) -> UsageRecord {
+        UsageRecord {
+            session_id: Some(session.to_string()),
+            process_id: format!("p-{}", session),
+            folder_path: "/work".to_string(),
+            provider: "claude_code".to_string(),
+            ralph_prd: prd.map(str::to_string),
+            ralph_iteration: iteration,
+            input_tokens: 100,
+            output_tokens: 10,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: 1000,
+            cost_usd: Some(cost),
+        }
+    }
+
+    #[test]
+    fn test_usage_aggregates() {
+        let conn = test_db();
+        record_usage(&conn, &record("plan", None, None, 0.5)).unwrap();
+        record_usage(&conn, &record("it1", Some("prd"), Some(1), 1.0)).unwrap();
+        record_usage(&conn, &record("it2", Some("prd"), Some(2), 2.0)).unwrap();
+        let all = DateRange::default();
+
+        let session = get_session_usage(&conn, "it1", &all).unwrap();
+        assert_eq!(session.runs, 1);
+        assert_eq!(session.total_tokens(), 1110);
+
+        let iteration = get_iteration_usage(&conn, "/work", "prd", 2, &all).unwrap();
+        assert_eq!(iteration.cost_usd, 2.0);
+
+        let prd = get_prd_usage(&conn, "/work", "prd", &all).unwrap();
+        assert_eq!(prd.runs, 2);
+        assert_eq!(prd.cost_usd, 3.0);
+
+        let folder = get_folder_usage(&conn, "/work", &all).unwrap();
+        assert_eq!(folder.runs, 3);
+        assert_eq!(folder.input_tokens, 300);
+
+        assert_eq!(
+            get_folder_usage(&conn, "/elsewhere", &all).unwrap(),
+            UsageTotals::default()
+        );
+    }
+
+    #[test]
+    fn test_usage_date_range() {
+        let conn = test_db();
+        record_usage(&conn, &record("s1", None, None, 1.0)).unwrap();
+
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+        let past = DateRange::parse(Some("2000-01-01"), Some(&today)).unwrap();
+        let current = DateRange::parse(Some(&today), None).unwrap();
+        assert_eq!(get_folder_usage(&conn, "/work", &past).unwrap().runs, 0);
+        assert_eq!(get_folder_usage(&conn, "/work", &current).unwrap().runs, 1);
+
+        // Bounds in other offsets are compared as the same instants
+        let hour = chrono::Duration::hours(1);
+        let from = (Utc::now() - hour).format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let to = (Utc::now() + hour)
+            .with_timezone(&chrono::FixedOffset::west_opt(7 * 3600).unwrap())
+            .to_rfc3339();
+        let around = DateRange::parse(Some(&from), Some(&to)).unwrap();
+        assert_eq!(get_folder_usage(&conn, "/work", &around).unwrap().runs, 1);
+        let before = DateRange::parse(None, Some(&from)).unwrap();
+        assert_eq!(get_folder_usage(&conn, "/work", &before).unwrap().runs, 0);
+
+        assert!(DateRange::parse(Some("yesterday"), None).is_err());
+        assert!(DateRange::parse(None, Some("2024-13-01")).is_err());
+    }
+}
@@ -4,11 +4,22 @@ mod models;
 mod platform;
 mod providers;
 mod state;
+#[cfg(feature = "replay")]
+#[doc(hidden)]
+pub mod test_support;
 mod utils;
 
 use tauri::menu::{Menu, MenuItemBuilder, SubmenuBuilder};
 use tauri::{Emitter, Manager};
 
+/// Play back a replay fixture when this binary was started as a replay provider child.
+/// Returns the exit code, or `None` for a normal app launch.
+#[cfg(feature = "replay")]
+pub fn run_replay() -> Option<i32> {
+    let args: Vec<String> = std::env::args().collect();
+    providers::replay::run_replay_from_args(&args)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -23,7 +34,19 @@ pub fn run() {
             // Provider commands
             commands::provider::run_provider,
             commands::provider::stop_provider,
+            commands::provider::list_queued_runs,
+            commands::provider::cancel_queued_run,
             commands::provider::check_provider_available,
+            commands::provider::select_ralph_provider,
+            commands::provider::get_provider_binary_path,
+            commands::provider::set_provider_binary_path,
+            commands::provider::get_provider_runner_mode,
+            commands::provider::set_provider_runner_mode,
+            commands::provider::list_providers,
+            commands::provider::get_process_info,
+            commands::provider::list_running_processes,
+            commands::provider::get_process_output,
+            commands::provider::get_run_log,
             // Plan file commands (filesystem)
             commands::plans::setup_folder,
             commands::plans::list_plans,
@@ -51,6 +74,32 @@ pub fn run() {
             commands::db::db_get_all_ralph_iterations,
             commands::db::db_get_folder_provider,
             commands::db::db_set_folder_provider,
+            commands::db::db_get_folder_model,
+            commands::db::db_set_folder_model,
+            commands::db::db_get_folder_env_profile,
+            commands::db::db_set_folder_env_profile,
+            commands::db::db_list_env_profiles,
+            commands::db::db_get_env_vars,
+            commands::db::db_set_env_var,
+            commands::db::db_delete_env_var,
+            commands::db::db_get_folder_permission_profile,
+            commands::db::db_set_folder_permission_profile,
+            commands::db::db_get_prd_permission_profile,
+            commands::db::db_set_prd_permission_profile,
+            commands::db::db_get_folder_max_concurrent_runs,
+            commands::db::db_set_folder_max_concurrent_runs,
+            commands::db::db_get_folder_fallback_providers,
+            commands::db::db_set_folder_fallback_providers,
+            commands::db::db_get_folder_budget,
+            commands::db::db_set_folder_budget,
+            commands::db::db_get_app_setting,
+            commands::db::db_set_app_setting,
+            commands::db::db_get_session_usage,
+            commands::db::db_get_iteration_usage,
+            commands::db::db_get_process_attempts,
+            commands::db::db_get_iteration_attempts,
+            commands::db::db_get_prd_usage,
+            commands::db::db_get_folder_usage,
             commands::db::db_update_session_display_name,
             commands::db::db_delete_session,
             commands::db::db_delete_ralph_prd_data
@@ -66,6 +115,17 @@ pub fn run() {
                 }
             }
 
+            // Capture the login shell environment in the background so the first
+            // run doesn't wait for it
+            std::thread::spawn(|| {
+                utils::shell_env::login_env();
+            });
+
+            // Register user-defined providers from ~/.trellico/providers.toml
+            if let Err(e) = providers::custom::load_custom_providers() {
+                eprintln!("Failed to load custom providers: {}", e);
+            }
+
             let main_window = app.get_webview_window("main").unwrap();
             platform::setup_macos_window(&main_window);
 
@@ -2,5 +2,11 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // The replay provider re-executes this binary to play back a fixture
+    #[cfg(feature = "replay")]
+    if let Some(code) = trellico_lib::run_replay() {
+        std::process::exit(code);
+    }
+
     trellico_lib::run()
 }
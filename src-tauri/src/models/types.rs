@@ -8,6 +8,78 @@ pub struct RalphIteration {
     pub provider: Option<String>,
 }
 
+// Identifies the Ralph iteration a provider run belongs to
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct RalphIterationRef {
+    pub prd_name: String,
+    pub iteration_number: i32,
+}
+
+// Optional context for a provider run (run_provider's `options` argument)
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+pub struct RunOptions {
+    pub session_type: Option<String>, // "plan" (default) | "ralph_prd"
+    pub ralph_iteration: Option<RalphIterationRef>,
+    pub user_message: Option<String>, // Shown to the user; saved as the run's first message
+    pub model: Option<String>,        // Overrides the folder's model
+    pub env_profile: Option<String>,  // Overrides the folder's env profile
+    pub max_duration: Option<u64>,    // Seconds; defaults to the `max_duration_secs` app setting
+    pub idle_timeout: Option<u64>,    // Seconds without output; defaults to `idle_timeout_secs`
+}
+
+// Live provider process info (returned by get_process_info)
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct ProcessInfo {
+    pub process_id: String,
+    pub provider: String,
+    pub folder_path: String,
+    pub session_type: String, // "plan" | "ralph_prd"
+    pub session_id: Option<String>,
+    pub model: Option<String>, // As requested, then as reported by the provider
+    pub permission_profile: crate::providers::PermissionProfile,
+    pub ralph_iteration: Option<RalphIterationRef>,
+}
+
+// A provider run that has left the queue (returned by list_running_processes,
+// sent with process-started once it spawns and process-updated on each retry)
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct RunningProcessInfo {
+    #[serde(flatten)]
+    pub info: ProcessInfo,
+    pub plan: Option<String>, // Plan the session is linked to
+    pub prd: Option<String>,  // PRD of the Ralph iteration, or the PRD the session is linked to
+    pub pid: Option<u32>,     // OS pid of the current attempt
+    pub started_at: String,
+    pub bytes_received: u64,
+    pub last_output_at: Option<String>,
+}
+
+// Something a provider process produced, as emitted live (ai-output, ai-event,
+// ai-stderr) and kept for get_process_output
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProcessOutput {
+    Output {
+        data: String,
+    },
+    Event {
+        event: crate::providers::stream::StreamEvent,
+    },
+    Stderr {
+        data: String,
+    },
+}
+
+// A provider run waiting for a slot (returned by list_queued_runs)
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct QueuedRunInfo {
+    #[serde(flatten)]
+    pub info: ProcessInfo,
+    pub priority: crate::utils::run_queue::RunPriority,
+    pub position: usize, // Queued runs ahead of this one
+    pub queued_secs: u64,
+}
+
 // Plan change event type
 #[derive(serde::Serialize, Clone)]
 pub struct PlanChangeEvent {
@@ -0,0 +1,129 @@
+use super::{
+    is_common_auth_error, LaunchOptions, PermissionProfile, ProviderBackend, RunnerMode, Version,
+    DEFAULT_STDIN_PROMPT_THRESHOLD,
+};
+use std::path::{Path, PathBuf};
+
+pub struct ClaudeCodeBackend;
+
+impl ProviderBackend for ClaudeCodeBackend {
+    fn id(&self) -> &str {
+        "claude_code"
+    }
+
+    fn display_name(&self) -> &str {
+        "Claude Code"
+    }
+
+    fn binary_name(&self) -> &str {
+        "claude"
+    }
+
+    fn install_url(&self) -> &str {
+        "https://claude.com/product/claude-code"
+    }
+
+    fn candidate_paths(&self, home: &str) -> Vec<PathBuf> {
+        vec![
+            PathBuf::from(format!("{}/.local/bin/claude", home)),
+            PathBuf::from("/usr/local/bin/claude"),
+            PathBuf::from("/opt/homebrew/bin/claude"),
+            PathBuf::from("/usr/bin/claude"),
+        ]
+    }
+
+    fn build_args(
+        &self,
+        message: &str,
+        session_id: Option<&str>,
+        launch: &LaunchOptions,
+    ) -> Vec<String> {
+        let mut args = vec!["-p".to_string()];
+
+        // The tool lists are variadic, so they must be followed by another flag
+        // rather than the prompt. In print mode any tool not allowed is denied.
+        let (allowed, denied) = launch.permissions.tool_rules();
+        if !allowed.is_empty() {
+            args.push("--allowedTools".to_string());
+            args.push(allowed.join(","));
+        }
+        if !denied.is_empty() {
+            args.push("--disallowedTools".to_string());
+            args.push(denied.join(","));
+        }
+        if launch.permissions == PermissionProfile::Full {
+            args.push("--dangerously-skip-permissions".to_string());
+        }
+
+        args.extend([
+            "--output-format".to_string(),
+            "stream-json".to_string(),
+            "--verbose".to_string(),
+        ]);
+
+        if let Some(model) = &launch.model {
+            args.push("--model".to_string());
+            args.push(model.clone());
+        }
+
+        if let Some(sid) = session_id {
+            args.push("--resume".to_string());
+            args.push(sid.to_string());
+        }
+
+        // `-p` without a prompt argument reads it from stdin
+        if !launch.prompt_via_stdin {
+            args.push(message.to_string());
+        }
+        args
+    }
+
+    fn stdin_prompt_threshold(&self) -> Option<usize> {
+        Some(DEFAULT_STDIN_PROMPT_THRESHOLD)
+    }
+
+    /// Print mode streams without a terminal
+    fn default_runner_mode(&self) -> RunnerMode {
+        RunnerMode::Pipe
+    }
+
+    /// `--verbose` stream-json output and the tool allow/deny lists
+    fn min_version(&self) -> Option<Version> {
+        Some(Version::new(1, 0, 0))
+    }
+
+    /// Denying the web tools would still leave Bash (curl, git push, ...)
+    /// able to reach the network, so that profile is left to sandboxed providers
+    fn supports_permissions(&self, profile: &PermissionProfile) -> bool {
+        *profile != PermissionProfile::NoNetworkTools
+    }
+
+    fn check_authenticated(&self) -> Result<(), String> {
+        let home = std::env::var("HOME").map_err(|_| "Cannot find home directory")?;
+
+        // Claude Code stores auth in ~/.claude/.credentials.json or similar
+        let credentials_path = format!("{}/.claude/.credentials.json", home);
+        let config_path = format!("{}/.claude.json", home);
+
+        // Check if either credential file exists
+        if Path::new(&credentials_path).exists() || Path::new(&config_path).exists() {
+            Ok(())
+        } else {
+            Err(self.not_logged_in_message())
+        }
+    }
+
+    fn is_auth_error(&self, output: &str) -> bool {
+        let lower = output.to_lowercase();
+        is_common_auth_error(&lower) || lower.contains("please run 'claude'")
+    }
+
+    fn not_logged_in_message(&self) -> String {
+        "Claude Code is not logged in. Please run 'claude' in your terminal to authenticate."
+            .to_string()
+    }
+
+    fn auth_instructions(&self) -> String {
+        "Run 'claude' in your terminal to authenticate".to_string()
+    }
+}
@@ -0,0 +1,244 @@
+use super::{
+    is_common_auth_error, LaunchOptions, PermissionProfile, ProviderBackend, RunnerMode, Version,
+    DEFAULT_STDIN_PROMPT_THRESHOLD,
+};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+pub struct CodexBackend;
+
+impl CodexBackend {
+    /// Codex keeps its state in $CODEX_HOME, defaulting to ~/.codex
+    fn codex_home(home: &str) -> PathBuf {
+        std::env::var("CODEX_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| Path::new(home).join(".codex"))
+    }
+
+    /// Build a Claude-style assistant message with a single content block
+    fn assistant(block: Value) -> Value {
+        json!({
+            "type": "assistant",
+            "message": { "role": "assistant", "content": [block] }
+        })
+    }
+
+    /// Map a completed Codex item onto a Claude-style assistant message
+    fn translate_item(item: &Value) -> Option<Value> {
+        let id = item.get("id").cloned().unwrap_or(Value::Null);
+        match item.get("type")?.as_str()? {
+            "agent_message" => Some(Self::assistant(json!({
+                "type": "text",
+                "text": item.get("text")?,
+            }))),
+            "reasoning" => Some(Self::assistant(json!({
+                "type": "thinking",
+                "thinking": item.get("text")?,
+            }))),
+            "command_execution" => Some(Self::assistant(json!({
+                "type": "tool_use",
+                "id": id,
+                "name": "Bash",
+                "input": {
+                    "command": item.get("command")?,
+                    "exit_code": item.get("exit_code"),
+                },
+            }))),
+            "file_change" => Some(Self::assistant(json!({
+                "type": "tool_use",
+                "id": id,
+                "name": "Edit",
+                "input": { "changes": item.get("changes")? },
+            }))),
+            "mcp_tool_call" => Some(Self::assistant(json!({
+                "type": "tool_use",
+                "id": id,
+                "name": item.get("tool")?,
+                "input": item.get("arguments").cloned().unwrap_or(json!({})),
+            }))),
+            "web_search" => Some(Self::assistant(json!({
+                "type": "tool_use",
+                "id": id,
+                "name": "WebSearch",
+                "input": { "query": item.get("query")? },
+            }))),
+            _ => None,
+        }
+    }
+
+    /// Build a Claude-style error result record
+    fn error_result(message: &str) -> Value {
+        json!({
+            "type": "result",
+            "subtype": "error",
+            "is_error": true,
+            "error": message,
+            "result": message,
+        })
+    }
+}
+
+impl ProviderBackend for CodexBackend {
+    fn id(&self) -> &str {
+        "codex"
+    }
+
+    fn display_name(&self) -> &str {
+        "Codex"
+    }
+
+    fn binary_name(&self) -> &str {
+        "codex"
+    }
+
+    fn install_url(&self) -> &str {
+        "https://github.com/openai/codex"
+    }
+
+    fn candidate_paths(&self, home: &str) -> Vec<PathBuf> {
+        vec![
+            PathBuf::from(format!("{}/.local/bin/codex", home)),
+            PathBuf::from(format!("{}/.npm-global/bin/codex", home)),
+            PathBuf::from(format!("{}/.bun/bin/codex", home)),
+            PathBuf::from("/usr/local/bin/codex"),
+            PathBuf::from("/opt/homebrew/bin/codex"),
+            PathBuf::from("/usr/bin/codex"),
+        ]
+    }
+
+    fn build_args(
+        &self,
+        message: &str,
+        session_id: Option<&str>,
+        launch: &LaunchOptions,
+    ) -> Vec<String> {
+        let mut args = vec![
+            "exec".to_string(),
+            "--json".to_string(),
+            "--skip-git-repo-check".to_string(),
+        ];
+
+        // Codex enforces profiles with its sandbox; workspace-write has no network
+        match launch.permissions {
+            PermissionProfile::NoNetworkTools => {
+                args.extend(["--sandbox".to_string(), "workspace-write".to_string()])
+            }
+            PermissionProfile::ReadOnly => {
+                args.extend(["--sandbox".to_string(), "read-only".to_string()])
+            }
+            _ => args.push("--dangerously-bypass-approvals-and-sandbox".to_string()),
+        }
+
+        // exec options must precede the resume subcommand
+        if let Some(model) = &launch.model {
+            args.push("--model".to_string());
+            args.push(model.clone());
+        }
+
+        // Continuation is a subcommand of exec that takes the thread id
+        if let Some(sid) = session_id {
+            args.push("resume".to_string());
+            args.push(sid.to_string());
+        }
+
+        // A prompt of `-` is read from stdin
+        if launch.prompt_via_stdin {
+            args.push("-".to_string());
+        } else {
+            args.push(message.to_string());
+        }
+        args
+    }
+
+    fn stdin_prompt_threshold(&self) -> Option<usize> {
+        Some(DEFAULT_STDIN_PROMPT_THRESHOLD)
+    }
+
+    /// `exec --json` is made for scripts
+    fn default_runner_mode(&self) -> RunnerMode {
+        RunnerMode::Pipe
+    }
+
+    /// `exec --json` emitting `thread.started` / `item.completed` events
+    fn min_version(&self) -> Option<Version> {
+        Some(Version::new(0, 44, 0))
+    }
+
+    fn supports_permissions(&self, profile: &PermissionProfile) -> bool {
+        !matches!(profile, PermissionProfile::Custom { .. })
+    }
+
+    fn check_authenticated(&self) -> Result<(), String> {
+        let home = std::env::var("HOME").map_err(|_| "Cannot find home directory")?;
+
+        // Either a ChatGPT/API-key login stored by `codex login`, or a key in the environment
+        let auth_path = Self::codex_home(&home).join("auth.json");
+        let has_env_key = ["OPENAI_API_KEY", "CODEX_API_KEY"]
+            .iter()
+            .any(|key| std::env::var(key).is_ok_and(|v| !v.is_empty()));
+
+        if auth_path.exists() || has_env_key {
+            Ok(())
+        } else {
+            Err(self.not_logged_in_message())
+        }
+    }
+
+    fn is_auth_error(&self, output: &str) -> bool {
+        let lower = output.to_lowercase();
+        is_common_auth_error(&lower)
+            || lower.contains("codex login")
+            || lower.contains("401 unauthorized")
+            || lower.contains("token_expired")
+            || lower.contains("refresh token")
+    }
+
+    fn not_logged_in_message(&self) -> String {
+        "Codex is not logged in. Please run 'codex login' to authenticate.".to_string()
+    }
+
+    fn auth_instructions(&self) -> String {
+        "Run 'codex login' to authenticate".to_string()
+    }
+
+    fn translate_line(&self, line: &str) -> Option<String> {
+        let record: Value = match serde_json::from_str(line) {
+            Ok(record) => record,
+            // Non-JSON output (warnings, banners) is passed through untouched
+            Err(_) => return Some(line.to_string()),
+        };
+
+        let translated = match record.get("type").and_then(Value::as_str)? {
+            "thread.started" => json!({
+                "type": "system",
+                "subtype": "init",
+                "session_id": record.get("thread_id")?,
+            }),
+            "item.completed" => Self::translate_item(record.get("item")?)?,
+            "turn.completed" => json!({
+                "type": "result",
+                "subtype": "success",
+                "is_error": false,
+                "usage": record.get("usage").cloned().unwrap_or(Value::Null),
+            }),
+            "turn.failed" => {
+                let message = record
+                    .pointer("/error/message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Turn failed");
+                Self::error_result(message)
+            }
+            "error" => {
+                let message = record
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Unknown error");
+                Self::error_result(message)
+            }
+            // turn.started, item.started and item.updated carry nothing the UI renders
+            _ => return None,
+        };
+
+        Some(translated.to_string())
+    }
+}
@@ -0,0 +1,337 @@
+use super::{is_common_auth_error, registry, LaunchOptions, ProviderBackend, RunnerMode};
+use crate::utils::paths::trellico_home;
+use crate::utils::shell_env;
+use serde::Deserialize;
+use serde_json::json;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+
+const PROVIDERS_FILE: &str = "providers.toml";
+
+/// How a custom provider writes its output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// Claude-compatible stream-json, passed through as-is
+    #[default]
+    StreamJson,
+    /// Plain text, each line wrapped into an assistant message
+    Text,
+}
+
+/// A user-defined provider entry from ~/.trellico/providers.toml
+///
+/// ```toml
+/// [[provider]]
+/// id = "aider"
+/// display_name = "Aider"
+/// binary = "~/.local/bin/aider"
+/// args = ["--yes-always", "--message", "{message}"]
+/// resume_args = ["--yes-always", "--restore-chat-history", "--message", "{message}"]
+/// auth_check = ["aider", "--check-model-accepts-settings"]
+/// output_format = "text"
+/// stdin_threshold = 65536
+/// runner = "pipe"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomProviderConfig {
+    pub id: String,
+    pub display_name: Option<String>,
+    /// Absolute path (`~` is expanded) or a bare binary name to search for
+    pub binary: String,
+    /// Argument template with `{message}`, `{session_id}` and `{model}` placeholders.
+    /// An argument whose value isn't set is left out, along with a flag right
+    /// before it (`"--session", "{session_id}"`).
+    pub args: Vec<String>,
+    /// Argument template used when continuing a session; falls back to `args`
+    pub resume_args: Option<Vec<String>>,
+    /// Command that exits successfully when the provider is authenticated
+    pub auth_check: Option<Vec<String>>,
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    /// Extra lowercase phrases that indicate an authentication failure
+    #[serde(default)]
+    pub auth_error_patterns: Vec<String>,
+    pub install_url: Option<String>,
+    /// Shown to the user when the provider is not logged in
+    pub login_hint: Option<String>,
+    /// Arguments used to verify the binary runs; empty skips the check
+    #[serde(default = "default_version_args")]
+    pub version_args: Vec<String>,
+    /// Prompts longer than this many bytes are written to stdin, dropping
+    /// arguments with a `{message}` placeholder; unset always uses arguments
+    pub stdin_threshold: Option<usize>,
+    /// "pty" (the default) or "pipe"; a text-output provider's stdout lines
+    /// are wrapped before the pipe runner's NDJSON check
+    #[serde(default)]
+    pub runner: RunnerMode,
+}
+
+fn default_version_args() -> Vec<String> {
+    vec!["--version".to_string()]
+}
+
+#[derive(Deserialize)]
+struct ProvidersFile {
+    #[serde(default)]
+    provider: Vec<CustomProviderConfig>,
+}
+
+/// Parse the contents of a providers file
+pub fn parse_providers_file(contents: &str) -> Result<Vec<CustomProviderConfig>, String> {
+    let file: ProvidersFile =
+        toml::from_str(contents).map_err(|e| format!("Invalid providers file: {}", e))?;
+    Ok(file.provider)
+}
+
+/// Load ~/.trellico/providers.toml (if present) and register its entries. Every
+/// entry is validated first, so an invalid one leaves none registered.
+/// Returns the ids that were registered.
+pub fn load_custom_providers() -> Result<Vec<String>, String> {
+    let path = trellico_home()?.join(PROVIDERS_FILE);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let mut backends = Vec::new();
+    for config in parse_providers_file(&contents)? {
+        let backend = CustomBackend::new(config)?;
+        // Built-in providers can't be shadowed, otherwise stored ids would change meaning
+        if registry::is_builtin(backend.id()) {
+            return Err(format!(
+                "Custom provider '{}' conflicts with a built-in provider",
+                backend.id()
+            ));
+        }
+        backends.push(backend);
+    }
+
+    let mut registered = Vec::new();
+    for backend in backends {
+        registered.push(backend.id().to_string());
+        registry::register(Arc::new(backend));
+    }
+
+    Ok(registered)
+}
+
+/// Expand a leading `~/` to the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// A provider backed by a user-defined command template
+pub struct CustomBackend {
+    config: CustomProviderConfig,
+    binary_name: String,
+}
+
+impl CustomBackend {
+    pub fn new(config: CustomProviderConfig) -> Result<Self, String> {
+        let valid_id = !config.id.is_empty()
+            && config
+                .id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+        if !valid_id {
+            return Err(format!(
+                "Invalid custom provider id '{}': use lowercase letters, digits, '_' or '-'",
+                config.id
+            ));
+        }
+        if config.binary.trim().is_empty() {
+            return Err(format!("Custom provider '{}' has no binary", config.id));
+        }
+
+        let binary_name = expand_home(&config.binary)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(&config.binary)
+            .to_string();
+
+        Ok(Self {
+            config,
+            binary_name,
+        })
+    }
+}
+
+impl ProviderBackend for CustomBackend {
+    fn id(&self) -> &str {
+        &self.config.id
+    }
+
+    fn display_name(&self) -> &str {
+        self.config
+            .display_name
+            .as_deref()
+            .unwrap_or(&self.config.id)
+    }
+
+    fn binary_name(&self) -> &str {
+        &self.binary_name
+    }
+
+    fn install_url(&self) -> &str {
+        self.config
+            .install_url
+            .as_deref()
+            .unwrap_or("~/.trellico/providers.toml")
+    }
+
+    fn candidate_paths(&self, home: &str) -> Vec<PathBuf> {
+        // An explicit path is the only candidate
+        if self.config.binary.contains('/') {
+            return vec![expand_home(&self.config.binary)];
+        }
+
+        vec![
+            PathBuf::from(format!("{}/.local/bin/{}", home, self.binary_name)),
+            PathBuf::from(format!("/usr/local/bin/{}", self.binary_name)),
+            PathBuf::from(format!("/opt/homebrew/bin/{}", self.binary_name)),
+            PathBuf::from(format!("/usr/bin/{}", self.binary_name)),
+        ]
+    }
+
+    fn build_args(
+        &self,
+        message: &str,
+        session_id: Option<&str>,
+        launch: &LaunchOptions,
+    ) -> Vec<String> {
+        let template = match (session_id, &self.config.resume_args) {
+            (Some(_), Some(resume_args)) => resume_args,
+            _ => &self.config.args,
+        };
+
+        let mut has_message = false;
+        let mut args: Vec<String> = Vec::new();
+        // Whether the last argument kept is a flag waiting for its value
+        let mut flag_pending = false;
+        for arg in template {
+            has_message |= arg.contains("{message}");
+            // Drop placeholders entirely when there is no session to resume,
+            // no model chosen or the prompt goes to stdin, leaving the CLI's
+            // own defaults. A value's flag goes with it, so `--session` can't
+            // take the next argument as its value.
+            if (session_id.is_none() && arg.contains("{session_id}"))
+                || (launch.model.is_none() && arg.contains("{model}"))
+                || (launch.prompt_via_stdin && arg.contains("{message}"))
+            {
+                if flag_pending && !arg.starts_with('-') {
+                    args.pop();
+                }
+                flag_pending = false;
+                continue;
+            }
+            flag_pending = arg.starts_with('-') && !arg.contains('=') && !arg.contains('{');
+            args.push(
+                arg.replace("{session_id}", session_id.unwrap_or_default())
+                    .replace("{model}", launch.model.as_deref().unwrap_or_default())
+                    .replace("{message}", message),
+            );
+        }
+
+        // Templates without a {message} placeholder take the prompt as the last argument
+        if !has_message && !launch.prompt_via_stdin {
+            args.push(message.to_string());
+        }
+
+        args
+    }
+
+    // Only a template with a {model} placeholder can pass a model on
+    fn supports_model(&self) -> bool {
+        std::iter::once(&self.config.args)
+            .chain(&self.config.resume_args)
+            .flatten()
+            .any(|arg| arg.contains("{model}"))
+    }
+
+    fn stdin_prompt_threshold(&self) -> Option<usize> {
+        self.config.stdin_threshold
+    }
+
+    fn default_runner_mode(&self) -> RunnerMode {
+        self.config.runner
+    }
+
+    fn check_authenticated(&self) -> Result<(), String> {
+        let Some((program, args)) = self
+            .config
+            .auth_check
+            .as_deref()
+            .and_then(|cmd| cmd.split_first())
+        else {
+            return Ok(());
+        };
+
+        // The same environment the provider itself runs with
+        match Command::new(expand_home(program))
+            .args(args)
+            .env_clear()
+            .envs(shell_env::login_env())
+            .output()
+        {
+            Ok(output) if output.status.success() => Ok(()),
+            _ => Err(self.not_logged_in_message()),
+        }
+    }
+
+    fn is_auth_error(&self, output: &str) -> bool {
+        let lower = output.to_lowercase();
+        is_common_auth_error(&lower)
+            || self
+                .config
+                .auth_error_patterns
+                .iter()
+                .any(|pattern| lower.contains(&pattern.to_lowercase()))
+    }
+
+    fn not_logged_in_message(&self) -> String {
+        format!(
+            "{} is not logged in. {}",
+            self.display_name(),
+            self.auth_instructions()
+        )
+    }
+
+    fn auth_instructions(&self) -> String {
+        self.config.login_hint.clone().unwrap_or_else(|| {
+            format!(
+                "Run '{}' in your terminal to authenticate",
+                self.binary_name
+            )
+        })
+    }
+
+    fn version_args(&self) -> Vec<String> {
+        self.config.version_args.clone()
+    }
+
+    fn translate_line(&self, line: &str) -> Option<String> {
+        match self.config.output_format {
+            OutputFormat::StreamJson => Some(line.to_string()),
+            OutputFormat::Text => {
+                if line.trim().is_empty() {
+                    return None;
+                }
+                let message = json!({
+                    "type": "assistant",
+                    "message": {
+                        "role": "assistant",
+                        "content": [{ "type": "text", "text": line }],
+                    },
+                });
+                Some(message.to_string())
+            }
+        }
+    }
+}
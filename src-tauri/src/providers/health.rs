@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Providers whose last run ended logged out or rate-limited. Each is skipped
+/// by Ralph's fallback chain until its cooldown passes or a run succeeds on it.
+#[derive(Default)]
+pub struct ProviderHealth {
+    /// Provider id -> when it failed, and why
+    unhealthy: HashMap<String, (Instant, String)>,
+}
+
+impl ProviderHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mark_unhealthy(&mut self, provider_id: &str, reason: String) {
+        self.unhealthy
+            .insert(provider_id.to_string(), (Instant::now(), reason));
+    }
+
+    pub fn mark_healthy(&mut self, provider_id: &str) {
+        self.unhealthy.remove(provider_id);
+    }
+
+    /// Why a provider should be skipped, if it failed less than `cooldown` ago
+    pub fn unhealthy_reason(&self, provider_id: &str, cooldown: Duration) -> Option<&str> {
+        self.unhealthy
+            .get(provider_id)
+            .filter(|(since, _)| since.elapsed() < cooldown)
+            .map(|(_, reason)| reason.as_str())
+    }
+}
+
+/// The providers to try, in order: the preferred one, then the folder's
+/// fallbacks (each only once)
+pub fn fallback_chain(preferred: &str, fallbacks: &[String]) -> Vec<String> {
+    let mut chain = vec![preferred.to_string()];
+    for id in fallbacks {
+        if !chain.contains(id) {
+            chain.push(id.clone());
+        }
+    }
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_chain() {
+        let fallbacks = vec![
+            "amp".to_string(),
+            "claude_code".to_string(),
+            "codex".to_string(),
+            "amp".to_string(),
+        ];
+        assert_eq!(
+            fallback_chain("claude_code", &fallbacks),
+            vec!["claude_code", "amp", "codex"]
+        );
+        assert_eq!(fallback_chain("amp", &[]), vec!["amp"]);
+    }
+
+    #[test]
+    fn test_unhealthy_until_cooldown_or_success() {
+        let mut health = ProviderHealth::new();
+        health.mark_unhealthy("claude_code", "rate limited".to_string());
+
+        let hour = Duration::from_secs(3600);
+        assert_eq!(
+            health.unhealthy_reason("claude_code", hour),
+            Some("rate limited")
+        );
+        assert_eq!(health.unhealthy_reason("claude_code", Duration::ZERO), None);
+        assert_eq!(health.unhealthy_reason("amp", hour), None);
+
+        health.mark_healthy("claude_code");
+        assert_eq!(health.unhealthy_reason("claude_code", hour), None);
+    }
+}
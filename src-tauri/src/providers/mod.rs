@@ -1,203 +1,331 @@
+mod amp;
+mod claude_code;
+mod codex;
+pub mod custom;
+pub mod health;
+pub mod permissions;
+pub mod registry;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod stream;
+pub mod usage;
+pub mod version;
+
+pub use permissions::PermissionProfile;
+pub use version::Version;
+
+use crate::db::settings;
+use crate::state::DB_CONNECTION;
+use crate::utils::shell_env;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Deref;
 use std::path::PathBuf;
-use std::process::Command;
+use std::sync::Arc;
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
-#[serde(rename_all = "snake_case")]
-pub enum Provider {
-    #[default]
-    ClaudeCode,
-    Amp,
+/// Per-run choices that change how a provider is launched
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+    /// Model to run; `None` uses the CLI's default
+    pub model: Option<String>,
+    /// Tools the agent may use
+    pub permissions: PermissionProfile,
+    /// Leave the prompt out of the arguments; it's written to stdin instead
+    pub prompt_via_stdin: bool,
 }
 
-impl Provider {
-    /// Find the binary for this provider by checking common installation paths.
-    /// GUI apps on macOS don't inherit the user's shell PATH, so we can't rely on `which`.
-    pub fn find_binary(&self) -> Option<PathBuf> {
-        let home = std::env::var("HOME").ok()?;
-
-        let candidates: Vec<String> = match self {
-            Provider::ClaudeCode => vec![
-                format!("{}/.local/bin/claude", home),
-                "/usr/local/bin/claude".to_string(),
-                "/opt/homebrew/bin/claude".to_string(),
-                "/usr/bin/claude".to_string(),
-            ],
-            Provider::Amp => vec![
-                format!("{}/.amp/bin/amp", home),
-                format!("{}/.local/bin/amp", home),
-                "/usr/local/bin/amp".to_string(),
-                "/opt/homebrew/bin/amp".to_string(),
-                "/usr/bin/amp".to_string(),
-            ],
-        };
+/// How a provider's process is attached to Trellico
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RunnerMode {
+    /// A pseudo-terminal, for CLIs that won't stream without a TTY. Stderr is
+    /// mixed into the output and the terminal is 80x24.
+    #[default]
+    Pty,
+    /// Plain pipes: stdout is parsed strictly as NDJSON and stderr is reported
+    /// on its own
+    Pipe,
+}
 
-        for path in &candidates {
-            let path = PathBuf::from(path);
-            if path.exists() {
-                return Some(path);
-            }
+impl RunnerMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pty" => Some(Self::Pty),
+            "pipe" => Some(Self::Pipe),
+            _ => None,
         }
+    }
 
-        // Fallback: try which (works in dev mode with inherited PATH)
-        let binary_name = self.binary_name();
-        if let Ok(output) = Command::new("which").arg(binary_name).output() {
-            if output.status.success() {
-                let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                if !path.is_empty() {
-                    return Some(PathBuf::from(path));
-                }
-            }
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pty => "pty",
+            Self::Pipe => "pipe",
         }
+    }
+}
+
+/// Prompt size, in bytes, above which providers that can read their prompt
+/// from stdin get it there. Comfortably below Linux's 128 KiB limit on a
+/// single argument.
+pub const DEFAULT_STDIN_PROMPT_THRESHOLD: usize = 32 * 1024;
+
+/// Behavior of a single agent CLI. Implementations are registered in the
+/// provider registry under their `id()`, which is also the string stored in
+/// `folder_settings.provider` and `sessions.provider`.
+pub trait ProviderBackend: Send + Sync {
+    /// Stable identifier used for serialization (e.g. "claude_code")
+    fn id(&self) -> &str;
+
+    /// Get human-readable display name
+    fn display_name(&self) -> &str;
+
+    /// Get the binary name for this provider
+    fn binary_name(&self) -> &str;
 
+    /// Get installation URL
+    fn install_url(&self) -> &str;
+
+    /// Common installation paths to check before falling back to `which`
+    fn candidate_paths(&self, home: &str) -> Vec<PathBuf>;
+
+    /// Build command arguments for running this provider
+    fn build_args(
+        &self,
+        message: &str,
+        session_id: Option<&str>,
+        launch: &LaunchOptions,
+    ) -> Vec<String>;
+
+    /// Prompts longer than this many bytes are written to stdin rather than
+    /// passed as an argument, which keeps them clear of `ARG_MAX` and out of
+    /// `ps`. `None` means the CLI only takes its prompt as an argument.
+    fn stdin_prompt_threshold(&self) -> Option<usize> {
         None
     }
 
-    /// Get the binary name for this provider
-    pub fn binary_name(&self) -> &'static str {
-        match self {
-            Provider::ClaudeCode => "claude",
-            Provider::Amp => "amp",
-        }
+    /// Whether `message` goes to stdin. Only Unix has the pipe it's written to;
+    /// elsewhere prompts always go on the command line.
+    fn prompt_via_stdin(&self, message: &str) -> bool {
+        cfg!(unix)
+            && self
+                .stdin_prompt_threshold()
+                .is_some_and(|threshold| message.len() > threshold)
     }
 
-    /// Build command arguments for running this provider
-    pub fn build_args(&self, message: &str, session_id: Option<&str>) -> Vec<String> {
-        match self {
-            Provider::ClaudeCode => {
-                let mut args = vec![
-                    "-p".to_string(),
-                    "--output-format".to_string(),
-                    "stream-json".to_string(),
-                    "--verbose".to_string(),
-                    "--dangerously-skip-permissions".to_string(),
-                ];
-
-                if let Some(sid) = session_id {
-                    args.push("--resume".to_string());
-                    args.push(sid.to_string());
-                }
-
-                args.push(message.to_string());
-                args
-            }
-            Provider::Amp => {
-                let mut args = if let Some(sid) = session_id {
-                    // Continuation uses different command structure
-                    vec![
-                        "threads".to_string(),
-                        "continue".to_string(),
-                        sid.to_string(),
-                        "-x".to_string(),
-                    ]
-                } else {
-                    vec!["-x".to_string()]
-                };
-
-                args.push(message.to_string());
-                args.push("--stream-json".to_string());
-                args.push("--dangerously-allow-all".to_string());
-                args
-            }
-        }
+    /// The runner used unless settings choose another
+    fn default_runner_mode(&self) -> RunnerMode {
+        RunnerMode::Pty
     }
 
-    /// Get human-readable display name
-    pub fn display_name(&self) -> &'static str {
-        match self {
-            Provider::ClaudeCode => "Claude Code",
-            Provider::Amp => "Amp",
-        }
+    /// The runner chosen for this provider in global settings, else its default
+    fn runner_mode(&self) -> RunnerMode {
+        runner_override(self.id()).unwrap_or_else(|| self.default_runner_mode())
     }
 
-    /// Get installation URL
-    pub fn install_url(&self) -> &'static str {
-        match self {
-            Provider::ClaudeCode => "https://claude.com/product/claude-code",
-            Provider::Amp => "https://ampcode.com",
+    /// Whether `build_args` can enforce this permission profile. Providers that
+    /// can't are refused rather than run with broader permissions.
+    fn supports_permissions(&self, profile: &PermissionProfile) -> bool {
+        *profile == PermissionProfile::Full
+    }
+
+    /// Whether `build_args` passes `LaunchOptions::model` on. Runs that chose
+    /// a model are refused by providers that would ignore it.
+    fn supports_model(&self) -> bool {
+        true
+    }
+
+    /// Check if the provider is authenticated by looking for config files or running a check command
+    fn check_authenticated(&self) -> Result<(), String>;
+
+    /// Detect authentication errors from provider output
+    fn is_auth_error(&self, output: &str) -> bool;
+
+    /// Detect failures worth retrying (rate limits, overloads, server errors)
+    /// from an error the provider reported
+    fn is_transient_error(&self, output: &str) -> bool {
+        is_common_transient_error(&output.to_lowercase())
+    }
+
+    /// Get error message for when the provider is not logged in
+    fn not_logged_in_message(&self) -> String;
+
+    /// Get auth instructions
+    fn auth_instructions(&self) -> String;
+
+    /// Arguments used to verify the binary runs; empty skips the check
+    fn version_args(&self) -> Vec<String> {
+        vec!["--version".to_string()]
+    }
+
+    /// Oldest CLI version that accepts every flag `build_args` emits; `None`
+    /// skips the check
+    fn min_version(&self) -> Option<Version> {
+        None
+    }
+
+    /// Hidden providers are resolvable by id but not offered in the provider picker
+    fn hidden(&self) -> bool {
+        false
+    }
+
+    /// Rewrite one line of provider output into the Claude stream-json dialect the UI
+    /// understands. Returning `None` drops the line.
+    fn translate_line(&self, line: &str) -> Option<String> {
+        Some(line.to_string())
+    }
+
+    /// Find the binary for this provider: the path set in settings, then common
+    /// installation paths, then the PATH from the user's login shell (GUI apps on
+    /// macOS don't inherit it).
+    fn find_binary(&self) -> Option<PathBuf> {
+        if let Some(path) = binary_override(self.id()) {
+            return Some(path);
         }
+
+        let home = std::env::var("HOME").ok()?;
+
+        for path in self.candidate_paths(&home) {
+            if path.exists() {
+                return Some(path);
+            }
+        }
+
+        which(self.binary_name())
     }
 
     /// Get error message for when the provider is not installed
-    pub fn not_installed_message(&self) -> String {
+    fn not_installed_message(&self) -> String {
         format!(
             "{} is not installed. Please install it from {}",
             self.display_name(),
             self.install_url()
         )
     }
+}
 
-    /// Get error message for when the provider is not logged in
-    pub fn not_logged_in_message(&self) -> &'static str {
-        match self {
-            Provider::ClaudeCode => {
-                "Claude Code is not logged in. Please run 'claude' in your terminal to authenticate."
-            }
-            Provider::Amp => "Amp is not logged in. Please run 'amp login' to authenticate.",
-        }
+/// The binary path configured for a provider in global settings, if any
+fn binary_override(provider_id: &str) -> Option<PathBuf> {
+    let conn = DB_CONNECTION.get()?;
+    settings::get_binary_path(conn, provider_id)
+        .ok()
+        .flatten()
+        .map(PathBuf::from)
+}
+
+/// The runner configured for a provider in global settings, if any
+fn runner_override(provider_id: &str) -> Option<RunnerMode> {
+    let conn = DB_CONNECTION.get()?;
+    settings::get_runner_mode(conn, provider_id).ok().flatten()
+}
+
+/// Resolve a binary name through the login shell's PATH
+pub(crate) fn which(binary_name: &str) -> Option<PathBuf> {
+    shell_env::find_in_path(binary_name)
+}
+
+/// Auth error phrases shared by every provider
+pub(crate) fn is_common_auth_error(lower: &str) -> bool {
+    lower.contains("not logged in")
+        || lower.contains("authentication")
+        || lower.contains("invalid api key")
+        || lower.contains("unauthorized")
+}
+
+/// Phrases for rate limits, overloads and server errors shared by every provider
+const TRANSIENT_ERROR_PHRASES: &[&str] = &[
+    "rate limit",
+    "rate_limit",
+    "too many requests",
+    "overloaded",
+    "internal server error",
+    "bad gateway",
+    "service unavailable",
+    "gateway timeout",
+    "connection reset",
+    "econnreset",
+];
+
+/// Text that precedes an HTTP status code in provider errors
+/// (e.g. "API Error: 529 ...", "last status: 429 Too Many Requests")
+const STATUS_PREFIXES: &[&str] = &["api error: ", "status: ", "status code ", "status "];
+
+/// Transient error phrases and status codes (429, 5xx) shared by every provider
+pub(crate) fn is_common_transient_error(lower: &str) -> bool {
+    if TRANSIENT_ERROR_PHRASES.iter().any(|p| lower.contains(p)) {
+        return true;
     }
 
-    /// Get auth instructions
-    pub fn auth_instructions(&self) -> &'static str {
-        match self {
-            Provider::ClaudeCode => "Run 'claude' in your terminal to authenticate",
-            Provider::Amp => "Run 'amp login' to authenticate",
-        }
+    STATUS_PREFIXES.iter().any(|prefix| {
+        lower.match_indices(prefix).any(|(index, _)| {
+            let code = &lower[index + prefix.len()..];
+            let code = code.get(..3).and_then(|c| c.parse::<u16>().ok());
+            matches!(code, Some(429 | 500..=599))
+        })
+    })
+}
+
+/// A handle to a registered provider backend.
+///
+/// Serializes to the backend id so the string form matches what is stored in the
+/// database; deserializing an id that isn't registered is an error.
+#[derive(Clone)]
+pub struct Provider(Arc<dyn ProviderBackend>);
+
+impl Provider {
+    pub const CLAUDE_CODE: &'static str = "claude_code";
+
+    /// Look up a provider by id in the registry
+    pub fn from_id(id: &str) -> Result<Self, String> {
+        registry::get(id)
+            .map(Provider)
+            .ok_or_else(|| format!("Unknown provider: {}", id))
     }
+}
 
-    /// Check if the provider is authenticated by looking for config files or running a check command
-    pub fn check_authenticated(&self) -> Result<(), String> {
-        let home = std::env::var("HOME").map_err(|_| "Cannot find home directory")?;
+impl Deref for Provider {
+    type Target = dyn ProviderBackend;
 
-        match self {
-            Provider::ClaudeCode => {
-                // Claude Code stores auth in ~/.claude/.credentials.json or similar
-                let credentials_path = format!("{}/.claude/.credentials.json", home);
-                let config_path = format!("{}/.claude.json", home);
-
-                // Check if either credential file exists
-                if std::path::Path::new(&credentials_path).exists()
-                    || std::path::Path::new(&config_path).exists()
-                {
-                    Ok(())
-                } else {
-                    Err(self.not_logged_in_message().to_string())
-                }
-            }
-            Provider::Amp => {
-                // Amp stores settings in ~/.config/amp/settings.json
-                // Auth is handled via browser-based login, so we just check if the config dir exists
-                let amp_settings = format!("{}/.config/amp/settings.json", home);
-
-                if std::path::Path::new(&amp_settings).exists() {
-                    Ok(())
-                } else {
-                    Err(self.not_logged_in_message().to_string())
-                }
-            }
-        }
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
     }
+}
 
-    /// Detect authentication errors from provider output
-    pub fn is_auth_error(&self, output: &str) -> bool {
-        let lower = output.to_lowercase();
-        match self {
-            Provider::ClaudeCode => {
-                lower.contains("not logged in")
-                    || lower.contains("authentication")
-                    || lower.contains("invalid api key")
-                    || lower.contains("unauthorized")
-                    || lower.contains("please run 'claude'")
-            }
-            Provider::Amp => {
-                lower.contains("not logged in")
-                    || lower.contains("authentication")
-                    || lower.contains("invalid api key")
-                    || lower.contains("unauthorized")
-                    || lower.contains("amp login")
-                    || lower.contains("please login")
-            }
-        }
+impl Default for Provider {
+    fn default() -> Self {
+        Provider::from_id(Provider::CLAUDE_CODE).expect("built-in provider is registered")
+    }
+}
+
+impl PartialEq for Provider {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl Eq for Provider {}
+
+impl fmt::Debug for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Provider").field(&self.id()).finish()
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+impl Serialize for Provider {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.id())
+    }
+}
+
+impl<'de> Deserialize<'de> for Provider {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Provider::from_id(&id).map_err(serde::de::Error::custom)
     }
 }
 
@@ -205,31 +333,56 @@ impl Provider {
 mod tests {
     use super::*;
 
+    fn claude() -> Provider {
+        Provider::from_id(Provider::CLAUDE_CODE).unwrap()
+    }
+
+    fn amp() -> Provider {
+        Provider::from_id("amp").unwrap()
+    }
+
+    fn codex() -> Provider {
+        Provider::from_id("codex").unwrap()
+    }
+
     #[test]
     fn test_provider_serde() {
-        let claude = Provider::ClaudeCode;
-        let serialized = serde_json::to_string(&claude).unwrap();
+        let serialized = serde_json::to_string(&claude()).unwrap();
         assert_eq!(serialized, "\"claude_code\"");
 
-        let amp = Provider::Amp;
-        let serialized = serde_json::to_string(&amp).unwrap();
+        let serialized = serde_json::to_string(&amp()).unwrap();
         assert_eq!(serialized, "\"amp\"");
 
         let deserialized: Provider = serde_json::from_str("\"claude_code\"").unwrap();
-        assert_eq!(deserialized, Provider::ClaudeCode);
+        assert_eq!(deserialized, claude());
 
         let deserialized: Provider = serde_json::from_str("\"amp\"").unwrap();
-        assert_eq!(deserialized, Provider::Amp);
+        assert_eq!(deserialized, amp());
+    }
+
+    #[test]
+    fn test_codex_serde() {
+        let serialized = serde_json::to_string(&codex()).unwrap();
+        assert_eq!(serialized, "\"codex\"");
+
+        let deserialized: Provider = serde_json::from_str("\"codex\"").unwrap();
+        assert_eq!(deserialized, codex());
+    }
+
+    #[test]
+    fn test_unknown_provider_rejected() {
+        assert!(serde_json::from_str::<Provider>("\"not_a_provider\"").is_err());
+        assert!(Provider::from_id("not_a_provider").is_err());
     }
 
     #[test]
     fn test_default_provider() {
-        assert_eq!(Provider::default(), Provider::ClaudeCode);
+        assert_eq!(Provider::default(), claude());
     }
 
     #[test]
     fn test_build_args_claude_new_session() {
-        let args = Provider::ClaudeCode.build_args("test message", None);
+        let args = claude().build_args("test message", None, &LaunchOptions::default());
         assert!(args.contains(&"-p".to_string()));
         assert!(args.contains(&"--output-format".to_string()));
         assert!(args.contains(&"stream-json".to_string()));
@@ -241,14 +394,18 @@ mod tests {
 
     #[test]
     fn test_build_args_claude_resume() {
-        let args = Provider::ClaudeCode.build_args("test message", Some("session-123"));
+        let args = claude().build_args(
+            "test message",
+            Some("session-123"),
+            &LaunchOptions::default(),
+        );
         assert!(args.contains(&"--resume".to_string()));
         assert!(args.contains(&"session-123".to_string()));
     }
 
     #[test]
     fn test_build_args_amp_new_session() {
-        let args = Provider::Amp.build_args("test message", None);
+        let args = amp().build_args("test message", None, &LaunchOptions::default());
         assert!(args.contains(&"-x".to_string()));
         assert!(args.contains(&"test message".to_string()));
         assert!(args.contains(&"--stream-json".to_string()));
@@ -258,11 +415,439 @@ mod tests {
 
     #[test]
     fn test_build_args_amp_resume() {
-        let args = Provider::Amp.build_args("test message", Some("thread-123"));
+        let args = amp().build_args(
+            "test message",
+            Some("thread-123"),
+            &LaunchOptions::default(),
+        );
         assert!(args.contains(&"threads".to_string()));
         assert!(args.contains(&"continue".to_string()));
         assert!(args.contains(&"thread-123".to_string()));
         assert!(args.contains(&"-x".to_string()));
         assert!(args.contains(&"test message".to_string()));
     }
+
+    #[test]
+    fn test_build_args_codex_new_session() {
+        let args = codex().build_args("test message", None, &LaunchOptions::default());
+        assert_eq!(args.first(), Some(&"exec".to_string()));
+        assert!(args.contains(&"--json".to_string()));
+        assert!(args.contains(&"--dangerously-bypass-approvals-and-sandbox".to_string()));
+        assert_eq!(args.last(), Some(&"test message".to_string()));
+        assert!(!args.contains(&"resume".to_string()));
+    }
+
+    #[test]
+    fn test_build_args_codex_resume() {
+        let args = codex().build_args(
+            "test message",
+            Some("thread-123"),
+            &LaunchOptions::default(),
+        );
+        let resume = args.iter().position(|a| a == "resume").unwrap();
+        assert_eq!(args[resume + 1], "thread-123");
+        assert!(args.contains(&"--json".to_string()));
+        assert_eq!(args.last(), Some(&"test message".to_string()));
+    }
+
+    #[test]
+    fn test_build_args_model() {
+        let launch = LaunchOptions {
+            model: Some("opus".to_string()),
+            ..Default::default()
+        };
+
+        let args = claude().build_args("test message", None, &launch);
+        let model = args.iter().position(|a| a == "--model").unwrap();
+        assert_eq!(args[model + 1], "opus");
+        assert_eq!(args.last(), Some(&"test message".to_string()));
+
+        // Amp selects models through its agent modes, which aren't model names
+        assert!(!amp().supports_model());
+        let args = amp().build_args("test message", None, &launch);
+        assert!(!args.contains(&"opus".to_string()));
+
+        let args = codex().build_args("test message", Some("thread-123"), &launch);
+        let model = args.iter().position(|a| a == "--model").unwrap();
+        assert!(model < args.iter().position(|a| a == "resume").unwrap());
+
+        let args = claude().build_args("test message", None, &LaunchOptions::default());
+        assert!(!args.contains(&"--model".to_string()));
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        for output in [
+            r#"API Error: 529 {"type":"error","error":{"type":"overloaded_error"}}"#,
+            "stream error: exceeded retry limit, last status: 429 Too Many Requests",
+            "API Error: 500 Internal Server Error",
+            "Rate limit exceeded, please retry",
+        ] {
+            assert!(claude().is_transient_error(output), "{}", output);
+            assert!(codex().is_transient_error(output), "{}", output);
+        }
+
+        for output in [
+            "API Error: 400 invalid_request_error",
+            "402 Payment Required",
+            "Invalid API key",
+            "Tests failed with status 1",
+        ] {
+            assert!(!claude().is_transient_error(output), "{}", output);
+        }
+    }
+
+    #[test]
+    fn test_build_args_permission_profiles() {
+        let launch = |permissions| LaunchOptions {
+            permissions,
+            ..Default::default()
+        };
+        let value_of = |args: &[String], flag: &str| {
+            let index = args.iter().position(|a| a == flag)?;
+            args.get(index + 1).cloned()
+        };
+
+        let args = claude().build_args("test message", None, &launch(PermissionProfile::ReadOnly));
+        assert!(!args.contains(&"--dangerously-skip-permissions".to_string()));
+        assert!(value_of(&args, "--allowedTools").unwrap().contains("Read"));
+        assert!(value_of(&args, "--disallowedTools")
+            .unwrap()
+            .contains("Bash"));
+        // The variadic tool lists must be followed by a flag, not the prompt
+        assert_eq!(args.last(), Some(&"test message".to_string()));
+        let disallowed = args.iter().position(|a| a == "--disallowedTools").unwrap();
+        assert!(args[disallowed + 2].starts_with("--"));
+
+        let custom = PermissionProfile::Custom {
+            allowed_tools: vec!["Read".to_string(), "Edit".to_string()],
+            bash_patterns: vec!["cargo test:*".to_string()],
+        };
+        let args = claude().build_args("test message", None, &launch(custom.clone()));
+        assert_eq!(
+            value_of(&args, "--allowedTools").as_deref(),
+            Some("Read,Edit,Bash(cargo test:*)")
+        );
+        assert!(!args.contains(&"--disallowedTools".to_string()));
+
+        let args = codex().build_args("test message", None, &launch(PermissionProfile::ReadOnly));
+        assert_eq!(value_of(&args, "--sandbox").as_deref(), Some("read-only"));
+        assert!(!args.contains(&"--dangerously-bypass-approvals-and-sandbox".to_string()));
+
+        assert!(claude().supports_permissions(&custom));
+        assert!(!codex().supports_permissions(&custom));
+        assert!(codex().supports_permissions(&PermissionProfile::NoNetworkTools));
+        assert!(!claude().supports_permissions(&PermissionProfile::NoNetworkTools));
+        assert!(!amp().supports_permissions(&PermissionProfile::ReadOnly));
+        assert!(amp().supports_permissions(&PermissionProfile::Full));
+    }
+
+    #[test]
+    fn test_translate_line_passthrough() {
+        let line = r#"{"type":"system","subtype":"init","session_id":"abc"}"#;
+        assert_eq!(claude().translate_line(line), Some(line.to_string()));
+    }
+
+    #[test]
+    fn test_translate_line_codex() {
+        let init = codex()
+            .translate_line(r#"{"type":"thread.started","thread_id":"thread-1"}"#)
+            .unwrap();
+        let init: serde_json::Value = serde_json::from_str(&init).unwrap();
+        assert_eq!(init["type"], "system");
+        assert_eq!(init["subtype"], "init");
+        assert_eq!(init["session_id"], "thread-1");
+
+        let text = codex()
+            .translate_line(
+                r#"{"type":"item.completed","item":{"id":"item_1","type":"agent_message","text":"done"}}"#,
+            )
+            .unwrap();
+        let text: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(text["type"], "assistant");
+        assert_eq!(text["message"]["content"][0]["text"], "done");
+
+        let failed = codex()
+            .translate_line(r#"{"type":"turn.failed","error":{"message":"boom"}}"#)
+            .unwrap();
+        let failed: serde_json::Value = serde_json::from_str(&failed).unwrap();
+        assert_eq!(failed["type"], "result");
+        assert_eq!(failed["is_error"], true);
+
+        assert!(codex()
+            .translate_line(r#"{"type":"turn.started"}"#)
+            .is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_large_prompts_go_to_stdin() {
+        // A pasted plan well past the per-argument limit
+        let large = "- [ ] implement the next step of the plan\n".repeat(20_000);
+        let stdin = LaunchOptions {
+            prompt_via_stdin: true,
+            ..Default::default()
+        };
+
+        for provider in [claude(), amp(), codex()] {
+            assert!(!provider.prompt_via_stdin("test message"));
+            assert!(provider.prompt_via_stdin(&large));
+
+            let args = provider.build_args(&large, Some("session-123"), &stdin);
+            assert!(args.iter().all(|arg| arg.len() < 1024), "{}", provider);
+        }
+
+        let args = claude().build_args(&large, None, &stdin);
+        assert_eq!(args.last(), Some(&"--verbose".to_string()));
+        let args = codex().build_args(&large, Some("thread-123"), &stdin);
+        assert_eq!(args.last(), Some(&"-".to_string()));
+
+        let backend = custom(
+            r#"
+            [[provider]]
+            id = "wrapper"
+            binary = "agent"
+            args = ["run", "--prompt={message}"]
+            stdin_threshold = 1024
+            "#,
+        );
+        assert!(backend.prompt_via_stdin(&large));
+        assert_eq!(backend.build_args(&large, None, &stdin), vec!["run"]);
+
+        #[cfg(feature = "replay")]
+        {
+            let replay = Provider::from_id("replay").unwrap();
+            assert!(!replay.prompt_via_stdin(&large));
+        }
+    }
+
+    #[test]
+    fn test_runner_modes() {
+        assert_eq!(claude().runner_mode(), RunnerMode::Pipe);
+        assert_eq!(codex().runner_mode(), RunnerMode::Pipe);
+        assert_eq!(amp().runner_mode(), RunnerMode::Pty);
+
+        let backend = custom(
+            r#"
+            [[provider]]
+            id = "wrapper"
+            binary = "agent"
+            args = []
+            runner = "pipe"
+            "#,
+        );
+        assert_eq!(backend.runner_mode(), RunnerMode::Pipe);
+
+        for mode in [RunnerMode::Pty, RunnerMode::Pipe] {
+            assert_eq!(RunnerMode::from_name(mode.name()), Some(mode));
+        }
+        assert_eq!(RunnerMode::from_name("tty"), None);
+    }
+
+    fn custom(toml: &str) -> custom::CustomBackend {
+        let config = custom::parse_providers_file(toml).unwrap().remove(0);
+        custom::CustomBackend::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_custom_provider_parse_defaults() {
+        let configs = custom::parse_providers_file(
+            r#"
+            [[provider]]
+            id = "aider"
+            binary = "aider"
+            args = ["--message", "{message}"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].output_format, custom::OutputFormat::StreamJson);
+        assert_eq!(configs[0].version_args, vec!["--version".to_string()]);
+        assert!(configs[0].resume_args.is_none());
+        assert_eq!(configs[0].runner, RunnerMode::Pty);
+    }
+
+    #[test]
+    fn test_custom_provider_invalid_id() {
+        let config = custom::parse_providers_file(
+            r#"
+            [[provider]]
+            id = "Not Valid"
+            binary = "aider"
+            args = []
+            "#,
+        )
+        .unwrap()
+        .remove(0);
+        assert!(custom::CustomBackend::new(config).is_err());
+    }
+
+    #[test]
+    fn test_build_args_custom_new_session() {
+        let backend = custom(
+            r#"
+            [[provider]]
+            id = "wrapper"
+            binary = "/opt/wrapper/bin/agent"
+            args = ["run", "--session", "{session_id}", "--prompt={message}"]
+            resume_args = ["resume", "{session_id}", "--prompt={message}"]
+            "#,
+        );
+        let args = backend.build_args("test message", None, &LaunchOptions::default());
+        assert_eq!(args, vec!["run", "--prompt=test message"]);
+        assert_eq!(backend.binary_name(), "agent");
+    }
+
+    #[test]
+    fn test_build_args_custom_resume() {
+        let backend = custom(
+            r#"
+            [[provider]]
+            id = "wrapper"
+            binary = "agent"
+            args = ["run", "{message}"]
+            resume_args = ["resume", "{session_id}", "{message}"]
+            "#,
+        );
+        let args = backend.build_args(
+            "test message",
+            Some("session-123"),
+            &LaunchOptions::default(),
+        );
+        assert_eq!(args, vec!["resume", "session-123", "test message"]);
+    }
+
+    #[test]
+    fn test_build_args_custom_model() {
+        let backend = custom(
+            r#"
+            [[provider]]
+            id = "wrapper"
+            binary = "agent"
+            args = ["--model={model}", "{message}"]
+            "#,
+        );
+        let launch = LaunchOptions {
+            model: Some("gpt-5".to_string()),
+            ..Default::default()
+        };
+        let args = backend.build_args("test message", None, &launch);
+        assert_eq!(args, vec!["--model=gpt-5", "test message"]);
+        assert!(backend.supports_model());
+
+        let args = backend.build_args("test message", None, &LaunchOptions::default());
+        assert_eq!(args, vec!["test message"]);
+    }
+
+    #[test]
+    fn test_build_args_custom_model_flag() {
+        let backend = custom(
+            r#"
+            [[provider]]
+            id = "wrapper"
+            binary = "agent"
+            args = ["run", "--model", "{model}", "{message}"]
+            "#,
+        );
+        let launch = LaunchOptions {
+            model: Some("gpt-5".to_string()),
+            ..Default::default()
+        };
+        let args = backend.build_args("test message", None, &launch);
+        assert_eq!(args, vec!["run", "--model", "gpt-5", "test message"]);
+
+        // Without a model the flag goes too, rather than taking the message as its value
+        let args = backend.build_args("test message", None, &LaunchOptions::default());
+        assert_eq!(args, vec!["run", "test message"]);
+    }
+
+    #[test]
+    fn test_build_args_custom_message_flag_via_stdin() {
+        let backend = custom(
+            r#"
+            [[provider]]
+            id = "wrapper"
+            binary = "agent"
+            args = ["run", "-p", "{message}", "--json"]
+            "#,
+        );
+        let args = backend.build_args("test message", None, &LaunchOptions::default());
+        assert_eq!(args, vec!["run", "-p", "test message", "--json"]);
+
+        // `-p` goes with the prompt, rather than taking `--json` as its value
+        let stdin = LaunchOptions {
+            prompt_via_stdin: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            backend.build_args("test message", None, &stdin),
+            vec!["run", "--json"]
+        );
+    }
+
+    #[test]
+    fn test_build_args_custom_appends_message() {
+        let backend = custom(
+            r#"
+            [[provider]]
+            id = "wrapper"
+            binary = "agent"
+            args = ["--json"]
+            "#,
+        );
+        let args = backend.build_args(
+            "test message",
+            Some("session-123"),
+            &LaunchOptions::default(),
+        );
+        assert_eq!(args, vec!["--json", "test message"]);
+        assert!(!backend.supports_model());
+    }
+
+    #[test]
+    fn test_custom_provider_text_output() {
+        let backend = custom(
+            r#"
+            [[provider]]
+            id = "plain"
+            binary = "agent"
+            args = []
+            output_format = "text"
+            "#,
+        );
+        let line = backend.translate_line("hello world").unwrap();
+        let line: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(line["type"], "assistant");
+        assert_eq!(line["message"]["content"][0]["text"], "hello world");
+        assert!(backend.translate_line("   ").is_none());
+    }
+
+    // Keeps a test's provider out of the shared registry once it finishes
+    struct Unregister(&'static str);
+
+    impl Drop for Unregister {
+        fn drop(&mut self) {
+            registry::unregister(self.0);
+        }
+    }
+
+    #[test]
+    fn test_custom_provider_registered() {
+        let backend = custom(
+            r#"
+            [[provider]]
+            id = "registered-agent"
+            binary = "agent"
+            args = []
+            "#,
+        );
+        registry::register(Arc::new(backend));
+        let guard = Unregister("registered-agent");
+        let provider: Provider = serde_json::from_str("\"registered-agent\"").unwrap();
+        assert_eq!(provider.id(), "registered-agent");
+        assert!(!registry::is_builtin("registered-agent"));
+        assert!(registry::is_builtin("codex"));
+        drop(guard);
+        assert!(registry::get("registered-agent").is_none());
+    }
 }
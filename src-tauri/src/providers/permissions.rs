@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+/// Tools that only read the workspace
+const READ_ONLY_TOOLS: &[&str] = &["Read", "Glob", "Grep", "LS", "TodoWrite"];
+
+/// Tools that modify the workspace or run commands
+const WRITE_TOOLS: &[&str] = &["Bash", "Edit", "MultiEdit", "Write", "NotebookEdit"];
+
+/// Tools that reach the network
+const NETWORK_TOOLS: &[&str] = &["WebFetch", "WebSearch"];
+
+/// What an agent is allowed to do during a run. Stored as JSON on folders,
+/// Ralph PRDs and sessions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum PermissionProfile {
+    /// Every tool, no prompts
+    #[default]
+    Full,
+    /// Every tool, without network access. Only providers that sandbox the
+    /// commands they run can enforce this.
+    NoNetworkTools,
+    /// Reading and searching files only
+    ReadOnly,
+    /// Only the listed tools, plus Bash commands matching the patterns
+    /// (e.g. `git diff:*`)
+    Custom {
+        #[serde(default)]
+        allowed_tools: Vec<String>,
+        #[serde(default)]
+        bash_patterns: Vec<String>,
+    },
+}
+
+impl PermissionProfile {
+    /// Short name shown in errors
+    pub fn name(&self) -> &'static str {
+        match self {
+            PermissionProfile::Full => "full",
+            PermissionProfile::NoNetworkTools => "no-network-tools",
+            PermissionProfile::ReadOnly => "read-only",
+            PermissionProfile::Custom { .. } => "custom",
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid permission profile: {}", e))
+    }
+
+    /// Tools to allow and deny, in Claude's tool naming. An empty allow list
+    /// means every tool not denied.
+    pub fn tool_rules(&self) -> (Vec<String>, Vec<String>) {
+        let owned = |tools: &[&str]| tools.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        match self {
+            PermissionProfile::Full => (vec![], vec![]),
+            PermissionProfile::NoNetworkTools => (vec![], owned(NETWORK_TOOLS)),
+            PermissionProfile::ReadOnly => (
+                owned(READ_ONLY_TOOLS),
+                owned(&[WRITE_TOOLS, NETWORK_TOOLS].concat()),
+            ),
+            PermissionProfile::Custom {
+                allowed_tools,
+                bash_patterns,
+            } => {
+                let allowed = allowed_tools
+                    .iter()
+                    .map(|tool| tool.trim().to_string())
+                    .filter(|tool| !tool.is_empty())
+                    .chain(
+                        bash_patterns
+                            .iter()
+                            .filter(|pattern| !pattern.trim().is_empty())
+                            .map(|pattern| format!("Bash({})", pattern.trim())),
+                    )
+                    .collect();
+                (allowed, vec![])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_json_round_trip() {
+        let custom = PermissionProfile::Custom {
+            allowed_tools: vec!["Read".to_string()],
+            bash_patterns: vec!["git diff:*".to_string()],
+        };
+        assert_eq!(
+            custom.to_json(),
+            r#"{"kind":"custom","allowed_tools":["Read"],"bash_patterns":["git diff:*"]}"#
+        );
+        assert_eq!(
+            PermissionProfile::from_json(&custom.to_json()).unwrap(),
+            custom
+        );
+        assert_eq!(
+            PermissionProfile::from_json(r#"{"kind":"read-only"}"#).unwrap(),
+            PermissionProfile::ReadOnly
+        );
+        assert!(PermissionProfile::from_json(r#"{"kind":"root"}"#).is_err());
+    }
+
+    #[test]
+    fn test_custom_tool_rules() {
+        let custom = PermissionProfile::Custom {
+            allowed_tools: vec!["Read".to_string(), " ".to_string()],
+            bash_patterns: vec!["npm test".to_string()],
+        };
+        let (allowed, denied) = custom.tool_rules();
+        assert_eq!(allowed, vec!["Read", "Bash(npm test)"]);
+        assert!(denied.is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+use super::amp::AmpBackend;
+use super::claude_code::ClaudeCodeBackend;
+use super::codex::CodexBackend;
+#[cfg(feature = "replay")]
+use super::replay::ReplayBackend;
+use super::ProviderBackend;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+
+// "replay" stays reserved in builds without it, so custom providers can't take the id
+const BUILTIN_IDS: [&str; 4] = ["claude_code", "amp", "codex", "replay"];
+
+// Provider backends keyed by id, seeded with the built-in providers
+static REGISTRY: LazyLock<RwLock<HashMap<String, Arc<dyn ProviderBackend>>>> =
+    LazyLock::new(|| {
+        let builtins: Vec<Arc<dyn ProviderBackend>> = vec![
+            Arc::new(ClaudeCodeBackend),
+            Arc::new(AmpBackend),
+            Arc::new(CodexBackend),
+            #[cfg(feature = "replay")]
+            Arc::new(ReplayBackend),
+        ];
+        let map = builtins
+            .into_iter()
+            .map(|backend| (backend.id().to_string(), backend))
+            .collect();
+        RwLock::new(map)
+    });
+
+/// Register a provider backend, replacing any existing backend with the same id
+pub fn register(backend: Arc<dyn ProviderBackend>) {
+    if let Ok(mut registry) = REGISTRY.write() {
+        registry.insert(backend.id().to_string(), backend);
+    }
+}
+
+/// Remove a provider backend registered by a test
+#[cfg(test)]
+pub(crate) fn unregister(id: &str) {
+    if let Ok(mut registry) = REGISTRY.write() {
+        registry.remove(id);
+    }
+}
+
+/// Whether an id belongs to a provider that ships with Trellico
+pub fn is_builtin(id: &str) -> bool {
+    BUILTIN_IDS.contains(&id)
+}
+
+/// Look up a provider backend by id
+pub fn get(id: &str) -> Option<Arc<dyn ProviderBackend>> {
+    REGISTRY.read().ok()?.get(id).cloned()
+}
+
+/// Get the ids of all registered providers, sorted
+pub fn ids() -> Vec<String> {
+    let mut ids: Vec<String> = REGISTRY
+        .read()
+        .map(|registry| registry.keys().cloned().collect())
+        .unwrap_or_default();
+    ids.sort();
+    ids
+}
@@ -0,0 +1,487 @@
+use super::{LaunchOptions, PermissionProfile, ProviderBackend};
+use crate::utils::paths::ralph_dir;
+use serde::Deserialize;
+use serde_json::Value;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Hidden argument that makes the trellico binary play back a fixture instead of starting the app
+pub const REPLAY_FLAG: &str = "--trellico-replay";
+
+/// Overrides the executable used to play fixtures (defaults to the running binary)
+static REPLAY_BIN: OnceLock<PathBuf> = OnceLock::new();
+
+/// Overrides the fixture path (defaults to `.trellico/replay.json` in the run's folder)
+const REPLAY_FIXTURE_ENV: &str = "TRELLICO_REPLAY_FIXTURE";
+
+/// A recorded transcript plus scripted behavior
+///
+/// ```json
+/// {
+///   "delay_ms": 20,
+///   "exit_code": 0,
+///   "steps": [
+///     { "emit": { "type": "system", "subtype": "init", "session_id": "{session_id}" } },
+///     { "emit": { "type": "assistant", "message": { "content": [{ "type": "text", "text": "Working" }] } }, "delay_ms": 500 },
+///     { "complete_next_story": "my-feature" },
+///     { "emit": { "type": "assistant", "message": { "content": [{ "type": "text", "text": "<promise>COMPLETE</promise>" }] } }, "only_if_complete": "my-feature" },
+///     { "fail": "Simulated crash", "exit_code": 2 }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ReplayFixture {
+    /// Delay before every step that doesn't set its own
+    pub delay_ms: u64,
+    /// Exit code when the transcript finishes without a failure
+    pub exit_code: i32,
+    pub steps: Vec<ReplayStep>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ReplayStep {
+    /// A stream-json record (or raw string) to write to stdout.
+    /// `{session_id}` and `{message}` are substituted inside strings.
+    pub emit: Option<Value>,
+    /// A line to write to stderr
+    pub stderr: Option<String>,
+    pub delay_ms: Option<u64>,
+    /// Mark a specific story in a PRD as passing or failing
+    pub set_passes: Option<SetPasses>,
+    /// Mark the highest-priority unfinished story in the named PRD as passing
+    pub complete_next_story: Option<String>,
+    /// Only run this step when every story in the named PRD passes
+    pub only_if_complete: Option<String>,
+    /// Abort the transcript, writing this message to stderr
+    pub fail: Option<String>,
+    /// Exit code used with `fail` (defaults to 1)
+    pub exit_code: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SetPasses {
+    pub prd: String,
+    pub story: String,
+    #[serde(default = "default_passes")]
+    pub passes: bool,
+}
+
+fn default_passes() -> bool {
+    true
+}
+
+/// Deterministic provider that plays back a recorded transcript, for offline testing
+pub struct ReplayBackend;
+
+/// Play fixtures with `binary` rather than the running executable, which in a
+/// test is the test harness. Only the first call takes effect.
+pub fn set_replay_binary(binary: PathBuf) {
+    let _ = REPLAY_BIN.set(binary);
+}
+
+impl ProviderBackend for ReplayBackend {
+    fn id(&self) -> &str {
+        "replay"
+    }
+
+    fn display_name(&self) -> &str {
+        "Replay"
+    }
+
+    fn binary_name(&self) -> &str {
+        "trellico"
+    }
+
+    fn install_url(&self) -> &str {
+        "https://github.com/gravelBridge/Trellico"
+    }
+
+    fn candidate_paths(&self, _home: &str) -> Vec<PathBuf> {
+        vec![]
+    }
+
+    fn find_binary(&self) -> Option<PathBuf> {
+        REPLAY_BIN
+            .get()
+            .cloned()
+            .or_else(|| std::env::current_exe().ok())
+    }
+
+    fn build_args(
+        &self,
+        message: &str,
+        session_id: Option<&str>,
+        _launch: &LaunchOptions,
+    ) -> Vec<String> {
+        let mut args = vec![REPLAY_FLAG.to_string()];
+
+        if let Ok(fixture) = std::env::var(REPLAY_FIXTURE_ENV) {
+            args.push("--fixture".to_string());
+            args.push(fixture);
+        }
+
+        if let Some(sid) = session_id {
+            args.push("--session".to_string());
+            args.push(sid.to_string());
+        }
+
+        args.push("--message".to_string());
+        args.push(message.to_string());
+        args
+    }
+
+    /// Playback runs no tools, so every profile is trivially enforced
+    fn supports_permissions(&self, _profile: &PermissionProfile) -> bool {
+        true
+    }
+
+    fn check_authenticated(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn is_auth_error(&self, _output: &str) -> bool {
+        false
+    }
+
+    fn not_logged_in_message(&self) -> String {
+        "Replay provider does not require authentication.".to_string()
+    }
+
+    fn auth_instructions(&self) -> String {
+        String::new()
+    }
+
+    fn version_args(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn hidden(&self) -> bool {
+        true
+    }
+}
+
+/// Entry point for the hidden replay mode. Returns `None` when the process
+/// wasn't started as a replay child, otherwise the exit code to use.
+pub fn run_replay_from_args(args: &[String]) -> Option<i32> {
+    if args.get(1).map(String::as_str) != Some(REPLAY_FLAG) {
+        return None;
+    }
+
+    let mut fixture_path = None;
+    let mut session_id = None;
+    let mut message = String::new();
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--fixture" => fixture_path = rest.next().map(PathBuf::from),
+            "--session" => session_id = rest.next().cloned(),
+            "--message" => message = rest.next().cloned().unwrap_or_default(),
+            _ => {}
+        }
+    }
+
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let fixture_path = fixture_path.unwrap_or_else(|| cwd.join(".trellico").join("replay.json"));
+
+    let fixture = match load_fixture(&fixture_path) {
+        Ok(fixture) => fixture,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Some(1);
+        }
+    };
+
+    let session_id = session_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let stdout = std::io::stdout();
+    let stderr = std::io::stderr();
+    Some(play_fixture(
+        &fixture,
+        &cwd,
+        &session_id,
+        &message,
+        &mut stdout.lock(),
+        &mut stderr.lock(),
+    ))
+}
+
+/// Read and parse a fixture file
+pub fn load_fixture(path: &Path) -> Result<ReplayFixture, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read replay fixture {}: {}", path.display(), e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Invalid replay fixture: {}", e))
+}
+
+/// Play a fixture against `folder_path`, writing output to the given streams.
+/// Returns the exit code.
+pub fn play_fixture(
+    fixture: &ReplayFixture,
+    folder_path: &Path,
+    session_id: &str,
+    message: &str,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> i32 {
+    let folder = folder_path.to_string_lossy();
+
+    for step in &fixture.steps {
+        if let Some(prd) = &step.only_if_complete {
+            if !prd_complete(&folder, prd) {
+                continue;
+            }
+        }
+
+        let delay = step.delay_ms.unwrap_or(fixture.delay_ms);
+        if delay > 0 {
+            std::thread::sleep(Duration::from_millis(delay));
+        }
+
+        if let Some(set) = &step.set_passes {
+            if let Err(e) = update_prd(
+                &folder,
+                &set.prd,
+                |stories| {
+                    stories
+                        .iter_mut()
+                        .find(|s| s.get("id").and_then(Value::as_str) == Some(set.story.as_str()))
+                },
+                set.passes,
+            ) {
+                let _ = writeln!(err, "{}", e);
+            }
+        }
+
+        if let Some(prd) = &step.complete_next_story {
+            if let Err(e) = update_prd(&folder, prd, next_unfinished_story, true) {
+                let _ = writeln!(err, "{}", e);
+            }
+        }
+
+        if let Some(emit) = &step.emit {
+            let line = match substitute(emit, session_id, message) {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            let _ = writeln!(out, "{}", line);
+            let _ = out.flush();
+        }
+
+        if let Some(line) = &step.stderr {
+            let _ = writeln!(err, "{}", line);
+            let _ = err.flush();
+        }
+
+        if let Some(failure) = &step.fail {
+            let _ = writeln!(err, "{}", failure);
+            let _ = err.flush();
+            return step.exit_code.unwrap_or(1);
+        }
+    }
+
+    fixture.exit_code
+}
+
+/// Replace `{session_id}` and `{message}` in every string of a JSON value
+fn substitute(value: &Value, session_id: &str, message: &str) -> Value {
+    match value {
+        Value::String(s) => Value::String(
+            s.replace("{session_id}", session_id)
+                .replace("{message}", message),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|v| substitute(v, session_id, message))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), substitute(v, session_id, message)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn prd_path(folder_path: &str, prd_name: &str) -> PathBuf {
+    ralph_dir(folder_path).join(prd_name).join("prd.json")
+}
+
+/// Pick the highest-priority story that doesn't pass yet
+fn next_unfinished_story(stories: &mut [Value]) -> Option<&mut Value> {
+    stories
+        .iter_mut()
+        .filter(|s| !s.get("passes").and_then(Value::as_bool).unwrap_or(false))
+        .min_by_key(|s| {
+            s.get("priority")
+                .and_then(Value::as_i64)
+                .unwrap_or(i64::MAX)
+        })
+}
+
+/// Set `passes` on the story chosen by `select` and write the PRD back
+fn update_prd(
+    folder_path: &str,
+    prd_name: &str,
+    select: impl FnOnce(&mut [Value]) -> Option<&mut Value>,
+    passes: bool,
+) -> Result<(), String> {
+    let path = prd_path(folder_path, prd_name);
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut prd: Value =
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid prd.json: {}", e))?;
+
+    let stories = prd
+        .get_mut("userStories")
+        .and_then(Value::as_array_mut)
+        .ok_or("prd.json has no userStories")?;
+
+    if let Some(story) = select(stories) {
+        story["passes"] = Value::Bool(passes);
+    }
+
+    let contents = serde_json::to_string_pretty(&prd)
+        .map_err(|e| format!("Failed to serialize prd.json: {}", e))?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write prd.json: {}", e))
+}
+
+/// Whether every story in a PRD passes
+fn prd_complete(folder_path: &str, prd_name: &str) -> bool {
+    std::fs::read_to_string(prd_path(folder_path, prd_name))
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        .and_then(|prd| {
+            prd.get("userStories")
+                .and_then(Value::as_array)
+                .map(|stories| {
+                    stories
+                        .iter()
+                        .all(|s| s.get("passes").and_then(Value::as_bool).unwrap_or(false))
+                })
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_folder(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("trellico-replay-{}-{}", name, uuid::Uuid::new_v4()));
+        let prd_dir = dir.join(".trellico").join("ralph").join("feature");
+        std::fs::create_dir_all(&prd_dir).unwrap();
+        let prd = json!({
+            "userStories": [
+                { "id": "US-002", "priority": 2, "passes": false },
+                { "id": "US-001", "priority": 1, "passes": false }
+            ]
+        });
+        std::fs::write(prd_dir.join("prd.json"), prd.to_string()).unwrap();
+        dir
+    }
+
+    fn play(fixture: Value, folder: &Path) -> (i32, String, String) {
+        let fixture: ReplayFixture = serde_json::from_value(fixture).unwrap();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let code = play_fixture(&fixture, folder, "sess-1", "hello", &mut out, &mut err);
+        (
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_replay_emits_lines_with_substitution() {
+        let folder = temp_folder("emit");
+        let (code, out, _) = play(
+            json!({
+                "exit_code": 3,
+                "steps": [
+                    { "emit": { "type": "system", "subtype": "init", "session_id": "{session_id}" } },
+                    { "emit": "raw {message}" }
+                ]
+            }),
+            &folder,
+        );
+        assert_eq!(code, 3);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let init: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(init["session_id"], "sess-1");
+        assert_eq!(lines[1], "raw hello");
+    }
+
+    #[test]
+    fn test_replay_injected_failure_stops_transcript() {
+        let folder = temp_folder("fail");
+        let (code, out, err) = play(
+            json!({
+                "steps": [
+                    { "emit": "before" },
+                    { "fail": "boom", "exit_code": 7 },
+                    { "emit": "after" }
+                ]
+            }),
+            &folder,
+        );
+        assert_eq!(code, 7);
+        assert_eq!(out, "before\n");
+        assert_eq!(err, "boom\n");
+    }
+
+    #[test]
+    fn test_replay_completes_stories_in_priority_order() {
+        let folder = temp_folder("stories");
+        let fixture = json!({
+            "steps": [
+                { "complete_next_story": "feature" },
+                { "emit": "<promise>COMPLETE</promise>", "only_if_complete": "feature" }
+            ]
+        });
+
+        let (_, out, _) = play(fixture.clone(), &folder);
+        assert_eq!(out, "");
+        let prd: Value = serde_json::from_str(
+            &std::fs::read_to_string(prd_path(&folder.to_string_lossy(), "feature")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(prd["userStories"][1]["passes"], true);
+        assert_eq!(prd["userStories"][0]["passes"], false);
+
+        let (_, out, _) = play(fixture, &folder);
+        assert_eq!(out, "<promise>COMPLETE</promise>\n");
+    }
+
+    #[test]
+    fn test_replay_set_passes() {
+        let folder = temp_folder("set");
+        play(
+            json!({ "steps": [{ "set_passes": { "prd": "feature", "story": "US-002" } }] }),
+            &folder,
+        );
+        assert!(!prd_complete(&folder.to_string_lossy(), "feature"));
+        play(
+            json!({ "steps": [{ "set_passes": { "prd": "feature", "story": "US-001" } }] }),
+            &folder,
+        );
+        assert!(prd_complete(&folder.to_string_lossy(), "feature"));
+    }
+
+    #[test]
+    fn test_replay_args_detection() {
+        let args: Vec<String> = ["trellico", REPLAY_FLAG, "--fixture", "/nonexistent.json"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(run_replay_from_args(&args), Some(1));
+        assert_eq!(run_replay_from_args(&["trellico".to_string()]), None);
+    }
+}
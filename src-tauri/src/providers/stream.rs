@@ -0,0 +1,395 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// Token counts reported by a provider
+#[derive(Clone, Debug, Default, Serialize, PartialEq, Eq)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+}
+
+impl Usage {
+    /// Parse a usage object. Claude and Amp use Anthropic field names; Codex
+    /// reports `cached_input_tokens` instead of `cache_read_input_tokens`.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let obj = value.as_object()?;
+        let field = |name: &str| obj.get(name).and_then(Value::as_u64).unwrap_or(0);
+        let usage = Usage {
+            input_tokens: field("input_tokens"),
+            output_tokens: field("output_tokens"),
+            cache_creation_input_tokens: field("cache_creation_input_tokens"),
+            cache_read_input_tokens: field("cache_read_input_tokens")
+                + field("cached_input_tokens"),
+        };
+        if usage == Usage::default() {
+            None
+        } else {
+            Some(usage)
+        }
+    }
+
+    pub fn add(&mut self, other: &Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+}
+
+/// A provider output record normalized across stream-json dialects
+#[derive(Clone, Debug, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// Session start; carries the id used to resume (Claude session / Amp thread)
+    Init {
+        session_id: String,
+        model: Option<String>,
+    },
+    Text {
+        text: String,
+        parent_tool_use_id: Option<String>,
+    },
+    Thinking {
+        text: String,
+    },
+    ToolUse {
+        id: Option<String>,
+        name: String,
+        input: Value,
+        parent_tool_use_id: Option<String>,
+    },
+    ToolResult {
+        tool_use_id: Option<String>,
+        content: Value,
+        is_error: bool,
+    },
+    /// Per-message token usage. Claude repeats a message's usage on every
+    /// record split from it, so `message_id` identifies duplicates.
+    Usage {
+        usage: Usage,
+        message_id: Option<String>,
+    },
+    /// End of a run
+    Result {
+        is_error: bool,
+        result: Option<String>,
+        error: Option<String>,
+        session_id: Option<String>,
+        duration_ms: Option<u64>,
+        num_turns: Option<u64>,
+        total_cost_usd: Option<f64>,
+        usage: Option<Usage>,
+    },
+    /// Anything not recognized, kept verbatim (non-JSON lines become a string)
+    Unknown {
+        raw: Value,
+    },
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+/// Parse one line of provider output into zero or more events.
+/// Blank lines produce nothing; everything else produces at least one event.
+pub fn parse_line(line: &str) -> Vec<StreamEvent> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return vec![];
+    }
+
+    match serde_json::from_str::<Value>(trimmed) {
+        Ok(record) => {
+            let events = parse_record(&record);
+            if events.is_empty() {
+                vec![StreamEvent::Unknown { raw: record }]
+            } else {
+                events
+            }
+        }
+        Err(_) => vec![StreamEvent::Unknown {
+            raw: Value::String(trimmed.to_string()),
+        }],
+    }
+}
+
+fn parse_record(record: &Value) -> Vec<StreamEvent> {
+    let record_type = record.get("type").and_then(Value::as_str).unwrap_or("");
+    let subtype = record.get("subtype").and_then(Value::as_str);
+
+    match (record_type, subtype) {
+        ("system", Some("init")) => {
+            // Amp may name it a thread id
+            let session_id =
+                str_field(record, "session_id").or_else(|| str_field(record, "thread_id"));
+            match session_id {
+                Some(session_id) => vec![StreamEvent::Init {
+                    session_id,
+                    model: str_field(record, "model"),
+                }],
+                None => vec![],
+            }
+        }
+        ("assistant", _) | ("user", _) => parse_message(record),
+        ("result", _) => vec![parse_result(record)],
+        _ => vec![],
+    }
+}
+
+/// Split a message record into one event per content block, plus usage
+fn parse_message(record: &Value) -> Vec<StreamEvent> {
+    let parent_tool_use_id = str_field(record, "parent_tool_use_id");
+    let is_assistant = record.get("type").and_then(Value::as_str) == Some("assistant");
+    let message = record.get("message").unwrap_or(&Value::Null);
+    let mut events = Vec::new();
+
+    match message.get("content") {
+        Some(Value::String(text)) if is_assistant => {
+            events.push(StreamEvent::Text {
+                text: text.clone(),
+                parent_tool_use_id: parent_tool_use_id.clone(),
+            });
+        }
+        Some(Value::Array(blocks)) => {
+            for block in blocks {
+                // User records only contribute tool results; their text is the prompt echo
+                if !is_assistant && block.get("type").and_then(Value::as_str) != Some("tool_result")
+                {
+                    continue;
+                }
+                if let Some(event) = parse_block(block, parent_tool_use_id.as_deref()) {
+                    events.push(event);
+                }
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(usage) = message.get("usage").and_then(Usage::from_value) {
+        events.push(StreamEvent::Usage {
+            usage,
+            message_id: str_field(message, "id"),
+        });
+    }
+
+    events
+}
+
+fn parse_block(block: &Value, parent_tool_use_id: Option<&str>) -> Option<StreamEvent> {
+    match block.get("type").and_then(Value::as_str)? {
+        "text" => Some(StreamEvent::Text {
+            text: str_field(block, "text")?,
+            parent_tool_use_id: parent_tool_use_id.map(str::to_string),
+        }),
+        "thinking" => Some(StreamEvent::Thinking {
+            text: str_field(block, "thinking").or_else(|| str_field(block, "text"))?,
+        }),
+        "tool_use" => Some(StreamEvent::ToolUse {
+            id: str_field(block, "id"),
+            name: str_field(block, "name")?,
+            input: block.get("input").cloned().unwrap_or(Value::Null),
+            parent_tool_use_id: parent_tool_use_id.map(str::to_string),
+        }),
+        "tool_result" => Some(StreamEvent::ToolResult {
+            tool_use_id: str_field(block, "tool_use_id"),
+            content: block.get("content").cloned().unwrap_or(Value::Null),
+            is_error: block
+                .get("is_error")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        }),
+        _ => None,
+    }
+}
+
+fn parse_result(record: &Value) -> StreamEvent {
+    let is_error = record
+        .get("is_error")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+        || record
+            .get("subtype")
+            .and_then(Value::as_str)
+            .is_some_and(|s| s.starts_with("error"));
+
+    // Claude reports errors in `result`, Amp in `error`
+    let result = str_field(record, "result");
+    let error = str_field(record, "error").or_else(|| if is_error { result.clone() } else { None });
+
+    StreamEvent::Result {
+        is_error,
+        result,
+        error,
+        session_id: str_field(record, "session_id"),
+        duration_ms: record.get("duration_ms").and_then(Value::as_u64),
+        num_turns: record.get("num_turns").and_then(Value::as_u64),
+        total_cost_usd: record.get("total_cost_usd").and_then(Value::as_f64),
+        usage: record.get("usage").and_then(Usage::from_value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_claude_init() {
+        let events = parse_line(
+            r#"{"type":"system","subtype":"init","session_id":"abc","model":"claude-sonnet-4-5","tools":[]}"#,
+        );
+        assert_eq!(
+            events,
+            vec![StreamEvent::Init {
+                session_id: "abc".to_string(),
+                model: Some("claude-sonnet-4-5".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_amp_init_thread_id() {
+        let events = parse_line(r#"{"type":"system","subtype":"init","thread_id":"T-123"}"#);
+        assert!(
+            matches!(&events[0], StreamEvent::Init { session_id, .. } if session_id == "T-123")
+        );
+    }
+
+    #[test]
+    fn test_parse_assistant_blocks_and_usage() {
+        let line = json!({
+            "type": "assistant",
+            "message": {
+                "role": "assistant",
+                "content": [
+                    { "type": "thinking", "thinking": "hmm" },
+                    { "type": "text", "text": "hello" },
+                    { "type": "tool_use", "id": "tu_1", "name": "Bash", "input": { "command": "ls" } }
+                ],
+                "usage": { "input_tokens": 10, "output_tokens": 5, "cache_read_input_tokens": 100 }
+            }
+        })
+        .to_string();
+
+        let events = parse_line(&line);
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[0],
+            StreamEvent::Thinking {
+                text: "hmm".to_string()
+            }
+        );
+        assert!(matches!(&events[1], StreamEvent::Text { text, .. } if text == "hello"));
+        assert!(matches!(&events[2], StreamEvent::ToolUse { name, .. } if name == "Bash"));
+        match &events[3] {
+            StreamEvent::Usage { usage, .. } => {
+                assert_eq!(usage.input_tokens, 10);
+                assert_eq!(usage.cache_read_input_tokens, 100);
+                assert_eq!(usage.output_tokens, 5);
+            }
+            other => panic!("expected usage, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_tool_result() {
+        let line = json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": [{ "type": "tool_result", "tool_use_id": "tu_1", "content": "ok", "is_error": true }]
+            }
+        })
+        .to_string();
+
+        let events = parse_line(&line);
+        assert_eq!(
+            events,
+            vec![StreamEvent::ToolResult {
+                tool_use_id: Some("tu_1".to_string()),
+                content: json!("ok"),
+                is_error: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_claude_result() {
+        let line = json!({
+            "type": "result",
+            "subtype": "success",
+            "is_error": false,
+            "result": "done",
+            "session_id": "abc",
+            "duration_ms": 1200,
+            "num_turns": 3,
+            "total_cost_usd": 0.42,
+            "usage": { "input_tokens": 100, "output_tokens": 50 }
+        })
+        .to_string();
+
+        match &parse_line(&line)[0] {
+            StreamEvent::Result {
+                is_error,
+                error,
+                total_cost_usd,
+                usage,
+                num_turns,
+                ..
+            } => {
+                assert!(!is_error);
+                assert!(error.is_none());
+                assert_eq!(*total_cost_usd, Some(0.42));
+                assert_eq!(*num_turns, Some(3));
+                assert_eq!(usage.as_ref().unwrap().output_tokens, 50);
+            }
+            other => panic!("expected result, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_amp_error_result() {
+        let line = r#"{"type":"result","subtype":"error_during_execution","is_error":true,"error":"402 Payment Required"}"#;
+        match &parse_line(line)[0] {
+            StreamEvent::Result {
+                is_error, error, ..
+            } => {
+                assert!(is_error);
+                assert_eq!(error.as_deref(), Some("402 Payment Required"));
+            }
+            other => panic!("expected result, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unknown_records_kept_verbatim() {
+        let events = parse_line(r#"{"type":"stream_event","foo":1}"#);
+        assert_eq!(
+            events,
+            vec![StreamEvent::Unknown {
+                raw: json!({ "type": "stream_event", "foo": 1 })
+            }]
+        );
+
+        let events = parse_line("Warning: something odd");
+        assert_eq!(
+            events,
+            vec![StreamEvent::Unknown {
+                raw: json!("Warning: something odd")
+            }]
+        );
+
+        assert!(parse_line("   ").is_empty());
+    }
+
+    #[test]
+    fn test_event_serialization_is_tagged() {
+        let event = StreamEvent::Thinking {
+            text: "x".to_string(),
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value, json!({ "kind": "thinking", "text": "x" }));
+    }
+}
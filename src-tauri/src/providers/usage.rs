@@ -0,0 +1,123 @@
+use super::stream::{StreamEvent, Usage};
+use std::collections::HashMap;
+
+/// Accumulates the token usage and cost a run reports while it streams
+#[derive(Debug, Default)]
+pub struct UsageTracker {
+    /// Latest usage per message; records without an id are kept individually
+    messages: HashMap<String, Usage>,
+    anonymous: Usage,
+    /// Totals from `result` records, which cover the whole turn
+    result_usage: Usage,
+    result_cost: Option<f64>,
+    has_result: bool,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&mut self, event: &StreamEvent) {
+        match event {
+            StreamEvent::Usage { usage, message_id } => match message_id {
+                Some(id) => {
+                    self.messages.insert(id.clone(), usage.clone());
+                }
+                None => self.anonymous.add(usage),
+            },
+            StreamEvent::Result {
+                usage,
+                total_cost_usd,
+                ..
+            } => {
+                self.has_result = true;
+                if let Some(usage) = usage {
+                    self.result_usage.add(usage);
+                }
+                if let Some(cost) = total_cost_usd {
+                    *self.result_cost.get_or_insert(0.0) += cost;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Totals for the run: what the result record reported, or the sum of
+    /// per-message usage when the run ended without one. Cost is only known
+    /// from result records. `None` if nothing was reported.
+    pub fn totals(&self) -> Option<(Usage, Option<f64>)> {
+        let usage = if self.has_result && self.result_usage != Usage::default() {
+            self.result_usage.clone()
+        } else {
+            let mut usage = self.anonymous.clone();
+            for message in self.messages.values() {
+                usage.add(message);
+            }
+            usage
+        };
+
+        if usage == Usage::default() && self.result_cost.is_none() {
+            None
+        } else {
+            Some((usage, self.result_cost))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::stream::parse_line;
+
+    fn observe_all(tracker: &mut UsageTracker, lines: &[&str]) {
+        for line in lines {
+            for event in parse_line(line) {
+                tracker.observe(&event);
+            }
+        }
+    }
+
+    #[test]
+    fn test_result_totals_win_over_message_usage() {
+        let mut tracker = UsageTracker::new();
+        observe_all(
+            &mut tracker,
+            &[
+                r#"{"type":"assistant","message":{"id":"m1","content":[{"type":"text","text":"a"}],"usage":{"input_tokens":10,"output_tokens":1}}}"#,
+                r#"{"type":"result","subtype":"success","total_cost_usd":0.25,"usage":{"input_tokens":100,"output_tokens":20}}"#,
+            ],
+        );
+
+        let (usage, cost) = tracker.totals().unwrap();
+        assert_eq!(usage.input_tokens, 100);
+        assert_eq!(usage.output_tokens, 20);
+        assert_eq!(cost, Some(0.25));
+    }
+
+    #[test]
+    fn test_message_usage_deduplicated_without_result() {
+        let mut tracker = UsageTracker::new();
+        // Claude splits one message into a record per content block, each repeating its usage
+        observe_all(
+            &mut tracker,
+            &[
+                r#"{"type":"assistant","message":{"id":"m1","content":[{"type":"thinking","thinking":"x"}],"usage":{"input_tokens":10,"output_tokens":2}}}"#,
+                r#"{"type":"assistant","message":{"id":"m1","content":[{"type":"text","text":"y"}],"usage":{"input_tokens":10,"output_tokens":5}}}"#,
+                r#"{"type":"assistant","message":{"id":"m2","content":[{"type":"text","text":"z"}],"usage":{"input_tokens":30,"output_tokens":3}}}"#,
+            ],
+        );
+
+        let (usage, cost) = tracker.totals().unwrap();
+        assert_eq!(usage.input_tokens, 40);
+        assert_eq!(usage.output_tokens, 8);
+        assert_eq!(cost, None);
+    }
+
+    #[test]
+    fn test_nothing_reported() {
+        let mut tracker = UsageTracker::new();
+        observe_all(&mut tracker, &[r#"{"type":"result","subtype":"success"}"#]);
+        assert!(tracker.totals().is_none());
+    }
+}
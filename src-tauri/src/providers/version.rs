@@ -0,0 +1,66 @@
+use std::fmt;
+
+/// A `major.minor.patch` CLI version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Find the first `major.minor[.patch]` in `--version` output, which
+    /// providers wrap in different text (e.g. "1.0.51 (Claude Code)",
+    /// "codex-cli 0.46.0")
+    pub fn parse(output: &str) -> Option<Self> {
+        output
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .find_map(|word| {
+                let mut parts = word.split('.').filter(|p| !p.is_empty());
+                let major = parts.next()?.parse().ok()?;
+                let minor = parts.next()?.parse().ok()?;
+                let patch = parts.next().and_then(|p| p.parse().ok()).unwrap_or(0);
+                Some(Self::new(major, minor, patch))
+            })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version_output() {
+        assert_eq!(
+            Version::parse("1.0.51 (Claude Code)"),
+            Some(Version::new(1, 0, 51))
+        );
+        assert_eq!(
+            Version::parse("codex-cli 0.46.0\n"),
+            Some(Version::new(0, 46, 0))
+        );
+        assert_eq!(Version::parse("tool v2.3"), Some(Version::new(2, 3, 0)));
+        assert_eq!(Version::parse("no version here 42"), None);
+    }
+
+    #[test]
+    fn test_version_ordering() {
+        assert!(Version::new(0, 9, 9) < Version::new(1, 0, 0));
+        assert!(Version::new(0, 44, 0) < Version::new(0, 46, 0));
+        assert_eq!(Version::new(1, 2, 3).to_string(), "1.2.3");
+    }
+}
@@ -1,13 +1,61 @@
 use crate::db::DbConnection;
+use crate::models::{ProcessInfo, ProcessOutput};
+use crate::providers::health::ProviderHealth;
+use crate::utils::output_buffer::OutputBuffer;
+use crate::utils::run_queue::RunQueue;
+use chrono::{DateTime, Utc};
 use notify::RecommendedWatcher;
 use std::collections::{HashMap, HashSet};
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, LazyLock, Mutex, OnceLock};
+use std::time::Instant;
 
-// AI processes - maps process_id to stop flag
-pub static AI_PROCESSES: LazyLock<Mutex<HashMap<String, Arc<AtomicBool>>>> =
+// A running AI process: its stop flag and what a view needs to reattach to it,
+// including its recent output
+pub struct AIProcess {
+    pub stop_flag: Arc<AtomicBool>,
+    pub info: ProcessInfo,
+    pub output: Arc<Mutex<OutputBuffer<ProcessOutput>>>,
+    pub activity: Arc<Mutex<ProcessActivity>>,
+}
+
+/// What a running AI process has been doing, for activity views
+#[derive(Clone, Default)]
+pub struct ProcessActivity {
+    pub started_at: DateTime<Utc>,
+    /// Of the current attempt; `None` between retries
+    pub pid: Option<u32>,
+    pub bytes_received: u64,
+    pub last_output_at: Option<DateTime<Utc>>,
+}
+
+impl ProcessActivity {
+    pub fn record_output(&mut self, bytes: usize) {
+        self.bytes_received += bytes as u64;
+        self.last_output_at = Some(Utc::now());
+    }
+}
+
+// AI processes - maps process_id to the running process
+pub static AI_PROCESSES: LazyLock<Mutex<HashMap<String, AIProcess>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
 
+// A provider run waiting in the run queue: the process to track once it
+// starts, and the work that runs it
+pub struct QueuedRun {
+    pub process: AIProcess,
+    pub queued_at: Instant,
+    pub start: Box<dyn FnOnce() + Send>,
+}
+
+// Provider runs waiting for (or holding) one of the concurrency slots
+pub static RUN_QUEUE: LazyLock<Mutex<RunQueue<QueuedRun>>> =
+    LazyLock::new(|| Mutex::new(RunQueue::new()));
+
+// Providers that recently ended a run logged out or rate-limited
+pub static PROVIDER_HEALTH: LazyLock<Mutex<ProviderHealth>> =
+    LazyLock::new(|| Mutex::new(ProviderHealth::new()));
+
 // Database connection (initialized once at startup)
 pub static DB_CONNECTION: OnceLock<DbConnection> = OnceLock::new();
 
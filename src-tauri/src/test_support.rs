@@ -0,0 +1,28 @@
+//! Entry points for the end-to-end tests in `tests/`; not part of the app
+
+use crate::commands::db::get_db;
+use crate::db::{self, messages};
+use crate::providers::replay;
+use crate::state::DB_CONNECTION;
+use crate::utils::paths;
+use std::path::Path;
+
+pub use crate::commands::provider::run_single_attempt;
+pub use crate::providers::Provider;
+
+/// Keep the database and run logs under `home`, play fixtures with
+/// `replay_binary`, and open the database as the app does at startup.
+/// Only the first call's paths take effect.
+pub fn init(home: &Path, replay_binary: &Path) -> Result<(), String> {
+    paths::set_home_override(home.to_path_buf());
+    replay::set_replay_binary(replay_binary.to_path_buf());
+    if DB_CONNECTION.get().is_none() {
+        let _ = DB_CONNECTION.set(db::init_db()?);
+    }
+    Ok(())
+}
+
+/// Every message saved for a session, oldest first
+pub fn session_messages(session_id: &str) -> Result<Vec<serde_json::Value>, String> {
+    messages::get_session_messages(get_db()?, session_id)
+}
@@ -0,0 +1,137 @@
+use super::paths::trellico_dir;
+use std::collections::BTreeMap;
+
+/// Profile whose variables apply to every run in a folder
+pub const DEFAULT_ENV_PROFILE: &str = "default";
+
+pub type EnvVars = BTreeMap<String, String>;
+
+/// Variables by profile name
+pub type EnvProfiles = BTreeMap<String, EnvVars>;
+
+/// Whether `key` is a portable environment variable name
+pub fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse a folder's `.trellico/env` file: dotenv lines, grouped into named
+/// profiles by `[name]` headers. Lines before the first header belong to the
+/// default profile.
+///
+/// ```text
+/// RUST_LOG=info
+///
+/// [proxy]
+/// export ANTHROPIC_BASE_URL="http://localhost:8080"
+/// ```
+///
+/// Errors name the line but never echo its value.
+pub fn parse_env_file(contents: &str) -> Result<EnvProfiles, String> {
+    let mut profiles = EnvProfiles::new();
+    let mut profile = DEFAULT_ENV_PROFILE.to_string();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(format!(
+                    "Invalid env file: empty profile name on line {}",
+                    index + 1
+                ));
+            }
+            profile = name.to_string();
+            profiles.entry(profile.clone()).or_default();
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!(
+                "Invalid env file: expected KEY=value on line {}",
+                index + 1
+            ));
+        };
+        let key = key.trim();
+        if !is_valid_key(key) {
+            return Err(format!(
+                "Invalid env file: bad variable name on line {}",
+                index + 1
+            ));
+        }
+
+        profiles
+            .entry(profile.clone())
+            .or_default()
+            .insert(key.to_string(), unquote(value.trim()).to_string());
+    }
+
+    Ok(profiles)
+}
+
+/// Strip one pair of matching quotes
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+/// Load `.trellico/env` from a folder; a missing file has no profiles
+pub fn load_env_file(folder_path: &str) -> Result<EnvProfiles, String> {
+    let path = trellico_dir(folder_path).join("env");
+    if !path.exists() {
+        return Ok(EnvProfiles::new());
+    }
+
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_env_file(&contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_file_profiles() {
+        let profiles = parse_env_file(
+            "# shared\nRUST_LOG=info\n\n[test]\nexport DATABASE_URL=\"postgres://localhost/test\"\nQUOTED='a b'\n[proxy]\nANTHROPIC_BASE_URL = http://localhost:8080\n",
+        )
+        .unwrap();
+
+        assert_eq!(profiles[DEFAULT_ENV_PROFILE]["RUST_LOG"], "info");
+        assert_eq!(
+            profiles["test"]["DATABASE_URL"],
+            "postgres://localhost/test"
+        );
+        assert_eq!(profiles["test"]["QUOTED"], "a b");
+        assert_eq!(
+            profiles["proxy"]["ANTHROPIC_BASE_URL"],
+            "http://localhost:8080"
+        );
+    }
+
+    #[test]
+    fn test_parse_env_file_errors_hide_values() {
+        let err = parse_env_file("TOKEN secret-value").unwrap_err();
+        assert!(err.contains("line 1"));
+        assert!(!err.contains("secret-value"));
+
+        assert!(parse_env_file("1BAD=x").is_err());
+        assert!(parse_env_file("[ ]").is_err());
+    }
+}
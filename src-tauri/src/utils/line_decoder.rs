@@ -0,0 +1,127 @@
+/// Reassembles lines from a byte stream whose reads can end anywhere, including
+/// in the middle of a multi-byte UTF-8 character. Bytes are buffered until a
+/// newline arrives; since `\n` never appears inside a multi-byte sequence, every
+/// complete line holds whole characters.
+#[derive(Default)]
+pub struct LineDecoder {
+    buf: Vec<u8>,
+}
+
+impl LineDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of bytes and return every line it completed, without the
+    /// trailing `\n` / `\r\n`
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let search_from = self.buf.len();
+        self.buf.extend_from_slice(chunk);
+
+        for i in search_from..self.buf.len() {
+            if self.buf[i] == b'\n' {
+                lines.push(decode_line(&self.buf[start..i]));
+                start = i + 1;
+            }
+        }
+
+        self.buf.drain(..start);
+        lines
+    }
+
+    /// Flush whatever is left once the stream has ended
+    pub fn finish(&mut self) -> Option<String> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        let line = decode_line(&self.buf);
+        self.buf.clear();
+        Some(line)
+    }
+}
+
+/// Decode one line, dropping a trailing `\r`. Invalid sequences (which can only
+/// come from the provider itself) are replaced rather than losing the line.
+fn decode_line(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &[&str] = &[
+        r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Done ✅ 🎉"}]}}"#,
+        r#"{"type":"assistant","message":{"content":[{"type":"text","text":"日本語のテキスト"}]}}"#,
+        r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Ünïcödé — “quotes” 👩‍💻"}]}}"#,
+        "plain ascii line",
+    ];
+
+    fn stream(lines: &[&str]) -> Vec<u8> {
+        lines
+            .iter()
+            .flat_map(|l| format!("{}\r\n", l).into_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_split_at_every_byte_offset() {
+        let bytes = stream(FIXTURES);
+        for split in 0..=bytes.len() {
+            let mut decoder = LineDecoder::new();
+            let mut lines = decoder.push(&bytes[..split]);
+            lines.extend(decoder.push(&bytes[split..]));
+            assert_eq!(lines, FIXTURES, "split at byte {}", split);
+            assert_eq!(decoder.finish(), None);
+        }
+    }
+
+    #[test]
+    fn test_single_byte_reads() {
+        let bytes = stream(FIXTURES);
+        let mut decoder = LineDecoder::new();
+        let lines: Vec<String> = bytes.iter().flat_map(|b| decoder.push(&[*b])).collect();
+        assert_eq!(lines, FIXTURES);
+    }
+
+    #[test]
+    fn test_every_pair_of_offsets_in_multibyte_line() {
+        let line = FIXTURES[2];
+        let bytes = stream(&[line]);
+        for a in 0..=bytes.len() {
+            for b in a..=bytes.len() {
+                let mut decoder = LineDecoder::new();
+                let mut lines = decoder.push(&bytes[..a]);
+                lines.extend(decoder.push(&bytes[a..b]));
+                lines.extend(decoder.push(&bytes[b..]));
+                assert_eq!(lines, vec![line], "splits at {} and {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_partial_line_held_until_newline() {
+        let mut decoder = LineDecoder::new();
+        assert!(decoder.push(b"{\"type\":").is_empty());
+        assert_eq!(decoder.push(b"\"x\"}\n{\"a\""), vec!["{\"type\":\"x\"}"]);
+        assert_eq!(decoder.finish(), Some("{\"a\"".to_string()));
+        assert_eq!(decoder.finish(), None);
+    }
+
+    #[test]
+    fn test_truncated_character_at_eof_is_replaced() {
+        let mut decoder = LineDecoder::new();
+        let emoji = "🎉".as_bytes();
+        assert!(decoder.push(&emoji[..2]).is_empty());
+        assert_eq!(decoder.finish(), Some("\u{FFFD}".to_string()));
+    }
+
+    #[test]
+    fn test_empty_lines_preserved() {
+        let mut decoder = LineDecoder::new();
+        assert_eq!(decoder.push(b"a\n\nb\n"), vec!["a", "", "b"]);
+    }
+}
@@ -1 +1,11 @@
+pub mod env_file;
+pub mod line_decoder;
+pub mod output_buffer;
 pub mod paths;
+pub mod process_group;
+#[cfg(unix)]
+pub mod prompt_pipe;
+pub mod retry;
+pub mod run_log;
+pub mod run_queue;
+pub mod shell_env;
@@ -0,0 +1,137 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// An entry and its position in everything a process has produced
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Buffered<T> {
+    pub offset: u64,
+    #[serde(flatten)]
+    pub entry: T,
+}
+
+/// What a view missed since an offset
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BufferedSince<T> {
+    pub entries: Vec<Buffered<T>>,
+    /// Where to continue from: live events below this offset are already in `entries`
+    pub next_offset: u64,
+    /// Entries after the requested offset that had already been dropped
+    pub dropped: u64,
+}
+
+/// The most recent output of a process, bounded by entry count and size.
+/// Offsets keep counting as old entries are dropped, so a view that catches
+/// up from here can tell which live events it has already seen.
+pub struct OutputBuffer<T> {
+    entries: VecDeque<(T, usize)>,
+    /// Offset of the oldest entry still held
+    first_offset: u64,
+    bytes: usize,
+    max_entries: usize,
+    max_bytes: usize,
+}
+
+impl<T: Clone> OutputBuffer<T> {
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            first_offset: 0,
+            bytes: 0,
+            max_entries,
+            max_bytes,
+        }
+    }
+
+    /// Offset the next entry will get
+    pub fn next_offset(&self) -> u64 {
+        self.first_offset + self.entries.len() as u64
+    }
+
+    /// Add an entry of roughly `size` bytes, dropping the oldest ones past
+    /// the limits (but never the new one). Returns the entry's offset.
+    pub fn push(&mut self, entry: T, size: usize) -> u64 {
+        let offset = self.next_offset();
+        self.entries.push_back((entry, size));
+        self.bytes += size;
+
+        while self.entries.len() > 1
+            && (self.entries.len() > self.max_entries || self.bytes > self.max_bytes)
+        {
+            if let Some((_, size)) = self.entries.pop_front() {
+                self.bytes -= size;
+                self.first_offset += 1;
+            }
+        }
+        offset
+    }
+
+    /// Every entry held from `offset` on
+    pub fn since(&self, offset: u64) -> BufferedSince<T> {
+        let start = offset.max(self.first_offset);
+        let skip = usize::try_from(start - self.first_offset).unwrap_or(usize::MAX);
+        BufferedSince {
+            entries: self
+                .entries
+                .iter()
+                .skip(skip)
+                .zip(start..)
+                .map(|((entry, _), offset)| Buffered {
+                    offset,
+                    entry: entry.clone(),
+                })
+                .collect(),
+            next_offset: self.next_offset().max(offset),
+            dropped: self.first_offset.saturating_sub(offset),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets(since: &BufferedSince<&str>) -> Vec<u64> {
+        since.entries.iter().map(|e| e.offset).collect()
+    }
+
+    #[test]
+    fn test_catch_up_from_offset() {
+        let mut buffer = OutputBuffer::new(10, 1024);
+        assert_eq!(buffer.push("a", 1), 0);
+        assert_eq!(buffer.push("b", 1), 1);
+        assert_eq!(buffer.push("c", 1), 2);
+
+        let all = buffer.since(0);
+        assert_eq!(offsets(&all), vec![0, 1, 2]);
+        assert_eq!(all.entries[1].entry, "b");
+        assert_eq!((all.next_offset, all.dropped), (3, 0));
+
+        let rest = buffer.since(2);
+        assert_eq!(offsets(&rest), vec![2]);
+
+        // Nothing new yet; the view keeps its place
+        let none = buffer.since(3);
+        assert!(none.entries.is_empty());
+        assert_eq!(none.next_offset, 3);
+        assert_eq!(buffer.since(7).next_offset, 7);
+    }
+
+    #[test]
+    fn test_drops_oldest_past_limits() {
+        let mut buffer = OutputBuffer::new(3, 100);
+        for entry in ["a", "b", "c", "d"] {
+            buffer.push(entry, 10);
+        }
+        let since = buffer.since(0);
+        assert_eq!(offsets(&since), vec![1, 2, 3]);
+        assert_eq!((since.next_offset, since.dropped), (4, 1));
+
+        // Size limit: the big entry pushes out everything before it, but is kept
+        assert_eq!(buffer.push("big", 500), 4);
+        let since = buffer.since(2);
+        assert_eq!(offsets(&since), vec![4]);
+        assert_eq!(since.dropped, 2);
+        assert_eq!(buffer.push("e", 10), 5);
+        assert_eq!(offsets(&buffer.since(0)), vec![5]);
+    }
+}
@@ -1,4 +1,39 @@
 use std::path::{Path, PathBuf};
+#[cfg(feature = "replay")]
+use std::sync::OnceLock;
+
+/// Home directory used instead of the user's, so end-to-end tests keep their
+/// database and logs to themselves
+#[cfg(feature = "replay")]
+static HOME_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Keep the global trellico directory under `home`. Only the first call takes effect.
+#[cfg(feature = "replay")]
+pub fn set_home_override(home: PathBuf) {
+    let _ = HOME_OVERRIDE.set(home);
+}
+
+fn home_dir() -> Option<PathBuf> {
+    #[cfg(feature = "replay")]
+    if let Some(home) = HOME_OVERRIDE.get() {
+        return Some(home.clone());
+    }
+    dirs::home_dir()
+}
+
+/// Get the global trellico directory (~/.trellico), creating it if needed
+pub fn trellico_home() -> Result<PathBuf, String> {
+    let home = home_dir().ok_or("Cannot find home directory")?;
+    let trellico_dir = home.join(".trellico");
+
+    // Create directory if it doesn't exist
+    if !trellico_dir.exists() {
+        std::fs::create_dir_all(&trellico_dir)
+            .map_err(|e| format!("Failed to create .trellico directory: {}", e))?;
+    }
+
+    Ok(trellico_dir)
+}
 
 /// Get the .trellico directory path
 pub fn trellico_dir(folder_path: &str) -> PathBuf {
@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+/// How long to wait after each signal before escalating to the next
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StopGrace {
+    /// After SIGINT, before SIGTERM
+    pub interrupt: Duration,
+    /// After SIGTERM, before SIGKILL
+    pub terminate: Duration,
+}
+
+impl Default for StopGrace {
+    fn default() -> Self {
+        Self {
+            interrupt: Duration::from_secs(3),
+            terminate: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Send a signal to every process in a group. Returns false once the group is gone.
+#[cfg(unix)]
+pub fn signal_group(pgid: u32, signal: i32) -> bool {
+    let Ok(pgid) = i32::try_from(pgid) else {
+        return false;
+    };
+    // SAFETY: kill(2) with a negative pid only signals the group; no memory is touched
+    unsafe { libc::kill(-pgid, signal) == 0 }
+}
+
+/// Stop a process group: SIGINT, then SIGTERM, then SIGKILL, waiting the
+/// grace period between each unless the group is gone or `exited` says so.
+///
+/// Provider processes are spawned as session (and process group) leaders, so
+/// this also reaches the tools they started.
+#[cfg(unix)]
+pub fn terminate_group(pgid: u32, grace: StopGrace, exited: impl Fn() -> bool) {
+    let steps = [
+        (libc::SIGINT, grace.interrupt),
+        (libc::SIGTERM, grace.terminate),
+    ];
+
+    for (signal, wait) in steps {
+        if exited() || !signal_group(pgid, signal) {
+            return;
+        }
+
+        let deadline = Instant::now() + wait;
+        while Instant::now() < deadline {
+            if exited() || !signal_group(pgid, 0) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    if !exited() {
+        signal_group(pgid, libc::SIGKILL);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::process::{CommandExt, ExitStatusExt};
+    use std::process::{Command, Stdio};
+
+    const SHORT_GRACE: StopGrace = StopGrace {
+        interrupt: Duration::from_millis(200),
+        terminate: Duration::from_millis(200),
+    };
+
+    /// Run a script in its own process group, returning once it has printed a line
+    fn spawn_group(script: &str) -> std::process::Child {
+        let mut child = Command::new("sh")
+            .args(["-c", script])
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let mut ready = String::new();
+        BufReader::new(child.stdout.as_mut().unwrap())
+            .read_line(&mut ready)
+            .unwrap();
+        child
+    }
+
+    #[test]
+    fn test_interrupt_stops_cooperative_process() {
+        let mut child = spawn_group("echo ready; exec sleep 30");
+        terminate_group(child.id(), SHORT_GRACE, || false);
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGINT));
+    }
+
+    #[test]
+    fn test_escalates_to_kill_when_signals_are_ignored() {
+        let mut child = spawn_group("trap '' INT TERM; echo ready; sleep 30");
+        let started = Instant::now();
+        terminate_group(child.id(), SHORT_GRACE, || false);
+        assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGKILL));
+        assert!(started.elapsed() >= SHORT_GRACE.interrupt + SHORT_GRACE.terminate);
+    }
+
+    #[test]
+    fn test_signals_reach_grandchildren() {
+        // The shell waits on a background sleep; both must die with the group
+        let mut child = spawn_group("sleep 30 & echo ready; wait");
+        let pgid = child.id();
+        terminate_group(pgid, SHORT_GRACE, || false);
+        child.wait().unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while signal_group(pgid, 0) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!signal_group(pgid, 0));
+    }
+}
@@ -0,0 +1,128 @@
+use std::ffi::CString;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+/// Opens the pipe as stdin and replaces itself with the provider, so the
+/// provider keeps the shell's PID (and the PTY's process group)
+const WRAPPER_SCRIPT: &str = r#"prompt=$1; shift; exec "$@" < "$prompt""#;
+
+/// A named pipe that carries a prompt to a provider's stdin. Providers run on a
+/// PTY, which can't take a long line of input or signal EOF after it, so the
+/// provider is started through a shell that redirects its stdin from the pipe
+/// instead. The prompt never touches the disk or the command line.
+pub struct PromptPipe {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl PromptPipe {
+    /// Create the pipe in a directory only this user can read
+    pub fn create() -> Result<Self, String> {
+        let dir = std::env::temp_dir().join(format!("trellico-prompt-{}", uuid::Uuid::new_v4()));
+        std::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+        let path = dir.join("prompt");
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| format!("Invalid prompt pipe path: {}", e))?;
+        // SAFETY: mkfifo only reads the NUL-terminated path
+        if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+            let e = std::io::Error::last_os_error();
+            let _ = std::fs::remove_dir(&dir);
+            return Err(format!("Failed to create prompt pipe: {}", e));
+        }
+
+        Ok(Self { dir, path })
+    }
+
+    /// The program and arguments that run `program args...` with the pipe as stdin
+    pub fn wrap_command(&self, program: &Path, args: &[String]) -> (String, Vec<String>) {
+        let mut wrapped = vec![
+            "-c".to_string(),
+            WRAPPER_SCRIPT.to_string(),
+            "sh".to_string(),
+            self.path.to_string_lossy().into_owned(),
+            program.to_string_lossy().into_owned(),
+        ];
+        wrapped.extend(args.iter().cloned());
+        ("/bin/sh".to_string(), wrapped)
+    }
+
+    /// Write `message` into the pipe from a background thread. The writer waits
+    /// for the provider to open the pipe, and closing it afterwards is the
+    /// provider's EOF.
+    pub fn spawn_writer(&self, message: String) {
+        let path = self.path.clone();
+        std::thread::spawn(move || {
+            let result = OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .and_then(|mut pipe| pipe.write_all(message.as_bytes()));
+            match result {
+                // A provider that exits without reading closes the pipe on us
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => {
+                    eprintln!("Failed to write prompt to stdin: {}", e)
+                }
+                _ => {}
+            }
+        });
+    }
+}
+
+impl Drop for PromptPipe {
+    fn drop(&mut self) {
+        // Release a writer still waiting for a reader, then clean up
+        let _ = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&self.path);
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_delivers_prompt_larger_than_arg_max() {
+        // Well past Linux's 128 KiB limit on a single argument
+        let prompt: String = "plan line with some detail\n".repeat(200_000);
+        let pipe = PromptPipe::create().unwrap();
+        let (program, args) = pipe.wrap_command(
+            Path::new("/bin/sh"),
+            &["-c".to_string(), "wc -c".to_string()],
+        );
+
+        pipe.spawn_writer(prompt.clone());
+        let output = Command::new(program).args(&args).output().unwrap();
+        assert!(output.status.success());
+        let bytes: usize = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .unwrap();
+        assert_eq!(bytes, prompt.len());
+
+        // The prompt isn't part of the command line
+        assert!(args.iter().all(|arg| arg.len() < 1024));
+    }
+
+    #[test]
+    fn test_drop_releases_unread_pipe() {
+        let pipe = PromptPipe::create().unwrap();
+        let dir = pipe.dir.clone();
+        pipe.spawn_writer("never read".repeat(100_000));
+
+        let started = Instant::now();
+        drop(pipe);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Longest wait a retry delay setting can ask for
+pub const MAX_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often a run that hit a transient failure is retried, and how long to
+/// wait before each retry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+    /// Wait before the first retry; doubles for each one after it
+    pub base_delay: Duration,
+    /// Longest wait between attempts
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(300),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry` (1 for the first retry)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+/// Sleep for `delay` unless `stop_flag` is raised first. Returns false if stopped.
+pub fn sleep_unless_stopped(delay: Duration, stop_flag: &AtomicBool) -> bool {
+    // A delay too long to represent waits until stopped
+    let deadline = Instant::now().checked_add(delay);
+    while deadline.is_none_or(|deadline| Instant::now() < deadline) {
+        if stop_flag.load(Ordering::SeqCst) {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    !stop_flag.load(Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_doubles_up_to_max() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_secs(10));
+        assert_eq!(policy.delay(2), Duration::from_secs(20));
+        assert_eq!(policy.delay(3), Duration::from_secs(40));
+        assert_eq!(policy.delay(10), Duration::from_secs(300));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_sleep_unless_stopped() {
+        let stop_flag = AtomicBool::new(false);
+        assert!(sleep_unless_stopped(Duration::from_millis(10), &stop_flag));
+
+        stop_flag.store(true, Ordering::SeqCst);
+        let started = Instant::now();
+        assert!(!sleep_unless_stopped(Duration::from_secs(10), &stop_flag));
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        assert!(!sleep_unless_stopped(Duration::MAX, &stop_flag));
+    }
+}
@@ -0,0 +1,366 @@
+use super::paths::trellico_home;
+use chrono::Utc;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How long raw run logs are kept, and how much space they may take in total.
+/// `None` keeps logs regardless of that limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogRetention {
+    pub max_age: Option<Duration>,
+    pub max_total_bytes: Option<u64>,
+}
+
+impl Default for LogRetention {
+    fn default() -> Self {
+        Self {
+            max_age: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            max_total_bytes: Some(1024 * 1024 * 1024),
+        }
+    }
+}
+
+/// Where every folder's run logs live (~/.trellico/logs)
+pub fn logs_root() -> Result<PathBuf, String> {
+    Ok(trellico_home()?.join("logs"))
+}
+
+/// A stable name for a folder's log directory. FNV-1a rather than std's
+/// hasher, whose output may change between Rust releases.
+pub fn folder_hash(folder_path: &str) -> String {
+    format!("{:016x}", fnv1a(folder_path.as_bytes()))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The log file for one run. Process IDs come from the frontend, so anything
+/// that could leave the folder's log directory is refused.
+pub fn log_path(folder_path: &str, process_id: &str) -> Result<PathBuf, String> {
+    if process_id.is_empty()
+        || !process_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(format!("Invalid process ID: {}", process_id));
+    }
+    Ok(logs_root()?
+        .join(folder_hash(folder_path))
+        .join(format!("{}.log", process_id)))
+}
+
+/// The command line with the prompt swapped for its size and hash, so logs
+/// show how a provider was started without repeating what it was asked
+pub fn redact_argv(program: &Path, args: &[String], prompt: &str) -> Vec<String> {
+    let placeholder = format!(
+        "<prompt: {} bytes, fnv1a {:016x}>",
+        prompt.len(),
+        fnv1a(prompt.as_bytes())
+    );
+    std::iter::once(program.to_string_lossy().into_owned())
+        .chain(args.iter().map(|arg| {
+            if prompt.is_empty() {
+                arg.clone()
+            } else {
+                arg.replace(prompt, &placeholder)
+            }
+        }))
+        .collect()
+}
+
+/// How an attempt was started, written before its output
+pub struct RunLogStart<'a> {
+    pub provider: &'a str,
+    pub argv: Vec<String>,
+    pub cwd: &'a str,
+    pub env_profile: &'a str,
+    pub runner: &'a str,
+}
+
+/// The append-only raw transcript of a run: a header per attempt, everything
+/// the provider wrote, and how the attempt ended. A log that can't be opened
+/// or written is reported once, and the rest of the run goes unlogged.
+pub struct RunLog {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl RunLog {
+    pub fn open(folder_path: &str, process_id: &str) -> Self {
+        match log_path(folder_path, process_id) {
+            Ok(path) => Self::open_at(path),
+            Err(e) => {
+                eprintln!("Failed to open run log: {}", e);
+                Self {
+                    path: PathBuf::new(),
+                    file: None,
+                }
+            }
+        }
+    }
+
+    fn open_at(path: PathBuf) -> Self {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let file = std::fs::create_dir_all(dir)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(&path));
+        if let Err(e) = &file {
+            eprintln!("Failed to open {}: {}", path.display(), e);
+        }
+        Self {
+            path,
+            file: file.ok(),
+        }
+    }
+
+    pub fn start(&mut self, start: &RunLogStart) {
+        let header = format!(
+            "==> {} started\nprovider: {}\nargv: {:?}\ncwd: {}\nenv profile: {}\nrunner: {}\n\n",
+            Utc::now().to_rfc3339(),
+            start.provider,
+            start.argv,
+            start.cwd,
+            start.env_profile,
+            start.runner
+        );
+        self.write(header.as_bytes());
+    }
+
+    /// Raw bytes from the provider's output, exactly as read
+    pub fn output(&mut self, bytes: &[u8]) {
+        self.write(bytes);
+    }
+
+    pub fn stderr(&mut self, line: &str) {
+        self.write(format!("[stderr] {}\n", line).as_bytes());
+    }
+
+    /// `exit_code` is `None` when the provider never started
+    pub fn end(&mut self, exit_code: Option<i32>, outcome: &str) {
+        let code = exit_code.map_or("none".to_string(), |code| code.to_string());
+        let footer = format!(
+            "\n==> {} ended: {}, exit code {}\n\n",
+            Utc::now().to_rfc3339(),
+            outcome,
+            code
+        );
+        self.write(footer.as_bytes());
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+        if let Err(e) = file.write_all(bytes) {
+            eprintln!("Failed to write {}: {}", self.path.display(), e);
+            self.file = None;
+        }
+    }
+}
+
+/// Part of a run log
+#[derive(Debug, Serialize, PartialEq)]
+pub struct LogContents {
+    pub path: String,
+    /// Size of the whole log, so a caller can tell whether it has grown
+    pub size: u64,
+    /// Where `content` starts in the log
+    pub offset: u64,
+    pub content: String,
+}
+
+/// Read a log, or only its last `tail_bytes`
+pub fn read_log(path: &Path, tail_bytes: Option<u64>) -> Result<LogContents, String> {
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let size = file
+        .metadata()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    let offset = tail_bytes.map_or(0, |tail| size.saturating_sub(tail));
+
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.take(size - offset).read_to_end(&mut bytes))
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    Ok(LogContents {
+        path: path.to_string_lossy().into_owned(),
+        size,
+        offset,
+        content: String::from_utf8_lossy(&bytes).into_owned(),
+    })
+}
+
+/// Delete logs older than the retention period, then the oldest logs until
+/// the rest fit the size limit. Returns how many were deleted.
+pub fn prune_logs(root: &Path, retention: &LogRetention, now: SystemTime) -> usize {
+    let Ok(folders) = std::fs::read_dir(root) else {
+        return 0;
+    };
+
+    let mut logs = Vec::new();
+    for folder in folders.flatten() {
+        let Ok(entries) = std::fs::read_dir(folder.path()) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "log") {
+                if let Ok(metadata) = entry.metadata() {
+                    let modified = metadata.modified().unwrap_or(now);
+                    logs.push((path, modified, metadata.len()));
+                }
+            }
+        }
+    }
+
+    // Newest first, so the size limit keeps the most recent runs
+    logs.sort_by(|a, b| b.1.cmp(&a.1));
+    let mut removed = 0;
+    let mut total_bytes = 0u64;
+    for (path, modified, len) in logs {
+        let age = now.duration_since(modified).unwrap_or_default();
+        let expired = retention.max_age.is_some_and(|max_age| age > max_age);
+        if !expired {
+            total_bytes = total_bytes.saturating_add(len);
+        }
+        let over_size = retention
+            .max_total_bytes
+            .is_some_and(|max| total_bytes > max);
+        if (expired || over_size) && std::fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("trellico-logs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_log_paths() {
+        assert_eq!(folder_hash("/work"), folder_hash("/work"));
+        assert_ne!(folder_hash("/work"), folder_hash("/work2"));
+        assert_eq!(folder_hash("").len(), 16);
+
+        let path = log_path("/work", "0f8c-42").unwrap();
+        assert!(path.ends_with(format!("logs/{}/0f8c-42.log", folder_hash("/work"))));
+        assert!(log_path("/work", "../../secrets").is_err());
+        assert!(log_path("/work", "").is_err());
+    }
+
+    #[test]
+    fn test_redact_argv() {
+        let prompt = "fix the failing test";
+        let args = vec![
+            "-p".to_string(),
+            prompt.to_string(),
+            format!("--prompt={}", prompt),
+        ];
+        let argv = redact_argv(Path::new("/bin/claude"), &args, prompt);
+        assert_eq!(argv[0], "/bin/claude");
+        assert_eq!(argv[1], "-p");
+        assert!(argv.iter().all(|arg| !arg.contains(prompt)));
+        assert!(argv[2].starts_with("<prompt: 20 bytes, fnv1a "));
+        assert!(argv[3].starts_with("--prompt=<prompt: "));
+    }
+
+    #[test]
+    fn test_log_appends_and_tails() {
+        let dir = temp_dir();
+        let path = dir.join("folder").join("run.log");
+
+        let mut log = RunLog::open_at(path.clone());
+        log.start(&RunLogStart {
+            provider: "claude_code",
+            argv: vec!["claude".to_string()],
+            cwd: "/work",
+            env_profile: "default",
+            runner: "pipe",
+        });
+        log.output(b"{\"type\":\"result\"}\n");
+        log.stderr("overloaded");
+        log.end(Some(1), "exited");
+        drop(log);
+
+        // A retry appends to the same log
+        let mut log = RunLog::open_at(path.clone());
+        log.output(b"second attempt\n");
+        drop(log);
+
+        let full = read_log(&path, None).unwrap();
+        assert_eq!(full.offset, 0);
+        assert_eq!(full.size, full.content.len() as u64);
+        assert!(full.content.contains("provider: claude_code\n"));
+        assert!(full
+            .content
+            .contains("{\"type\":\"result\"}\n[stderr] overloaded\n"));
+        assert!(full.content.contains("ended: exited, exit code 1"));
+
+        let tail = read_log(&path, Some(15)).unwrap();
+        assert_eq!(tail.content, "second attempt\n");
+        assert_eq!(tail.offset, full.size - 15);
+        assert_eq!(read_log(&path, Some(u64::MAX)).unwrap(), full);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune_logs() {
+        let root = temp_dir();
+        let folder = root.join("abc");
+        std::fs::create_dir_all(&folder).unwrap();
+        let now = SystemTime::now();
+        for (name, len, age_secs) in [
+            ("old.log", 10, 30),
+            ("mid.log", 30, 20),
+            ("new.log", 30, 10),
+        ] {
+            let path = folder.join(name);
+            std::fs::write(&path, vec![b'x'; len]).unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(now - Duration::from_secs(age_secs))
+                .unwrap();
+        }
+        std::fs::write(folder.join("notes.txt"), "kept").unwrap();
+
+        let keep_all = LogRetention {
+            max_age: None,
+            max_total_bytes: None,
+        };
+        assert_eq!(prune_logs(&root, &keep_all, now), 0);
+
+        // Only the newest logs that fit stay
+        let limited = LogRetention {
+            max_age: None,
+            max_total_bytes: Some(45),
+        };
+        assert_eq!(prune_logs(&root, &limited, now), 2);
+        assert!(folder.join("new.log").exists());
+        assert!(!folder.join("mid.log").exists());
+        assert!(!folder.join("old.log").exists());
+
+        let later = now + Duration::from_secs(3600);
+        let expiring = LogRetention {
+            max_age: Some(Duration::from_secs(60)),
+            max_total_bytes: None,
+        };
+        assert_eq!(prune_logs(&root, &expiring, later), 1);
+        assert!(folder.join("notes.txt").exists());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
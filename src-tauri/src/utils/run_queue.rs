@@ -0,0 +1,171 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Which queued runs start first. Interactive chat is ahead of Ralph
+/// iterations, which can wait without anyone watching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunPriority {
+    Interactive,
+    Ralph,
+}
+
+/// A run waiting for a slot
+pub struct Queued<J> {
+    pub id: String,
+    pub folder_path: String,
+    pub priority: RunPriority,
+    pub job: J,
+}
+
+/// Provider runs waiting to start, and the ones holding a slot. Limits are
+/// passed in on every call so changes to settings apply to the next dispatch.
+pub struct RunQueue<J> {
+    /// Ordered by priority, then submission
+    pending: Vec<Queued<J>>,
+    /// Running id -> folder
+    running: HashMap<String, String>,
+}
+
+impl<J> Default for RunQueue<J> {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            running: HashMap::new(),
+        }
+    }
+}
+
+impl<J> RunQueue<J> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a run; returns how many queued runs are ahead of it
+    pub fn push(
+        &mut self,
+        id: String,
+        folder_path: String,
+        priority: RunPriority,
+        job: J,
+    ) -> usize {
+        // After every run of the same or higher priority
+        let position = self.pending.partition_point(|q| q.priority <= priority);
+        self.pending.insert(
+            position,
+            Queued {
+                id,
+                folder_path,
+                priority,
+                job,
+            },
+        );
+        position
+    }
+
+    /// Remove every run that may start now and mark it running. A run whose
+    /// folder is at its limit doesn't hold back runs from other folders.
+    pub fn take_ready(
+        &mut self,
+        global_limit: usize,
+        folder_limit: impl Fn(&str) -> Option<usize>,
+    ) -> Vec<Queued<J>> {
+        let mut ready = Vec::new();
+        let mut index = 0;
+
+        while index < self.pending.len() && self.running.len() < global_limit {
+            let folder = &self.pending[index].folder_path;
+            let in_folder = self.running.values().filter(|f| *f == folder).count();
+            if folder_limit(folder).is_some_and(|limit| in_folder >= limit) {
+                index += 1;
+                continue;
+            }
+
+            let run = self.pending.remove(index);
+            self.running.insert(run.id.clone(), run.folder_path.clone());
+            ready.push(run);
+        }
+
+        ready
+    }
+
+    /// Release a running run's slot
+    pub fn finish(&mut self, id: &str) {
+        self.running.remove(id);
+    }
+
+    /// Take a run out of the queue before it starts
+    pub fn cancel(&mut self, id: &str) -> Option<Queued<J>> {
+        let index = self.pending.iter().position(|q| q.id == id)?;
+        Some(self.pending.remove(index))
+    }
+
+    /// Take every queued run out of the queue
+    pub fn cancel_all(&mut self) -> Vec<Queued<J>> {
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Queued runs in the order they will start
+    pub fn pending(&self) -> impl Iterator<Item = &Queued<J>> {
+        self.pending.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids<J>(runs: &[Queued<J>]) -> Vec<&str> {
+        runs.iter().map(|r| r.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_interactive_runs_jump_ahead_of_ralph() {
+        let mut queue = RunQueue::new();
+        assert_eq!(
+            queue.push("r1".into(), "/a".into(), RunPriority::Ralph, ()),
+            0
+        );
+        assert_eq!(
+            queue.push("r2".into(), "/a".into(), RunPriority::Ralph, ()),
+            1
+        );
+        assert_eq!(
+            queue.push("chat".into(), "/a".into(), RunPriority::Interactive, ()),
+            0
+        );
+
+        let ready = queue.take_ready(2, |_| None);
+        assert_eq!(ids(&ready), vec!["chat", "r1"]);
+
+        // Full until a slot is released
+        assert!(queue.take_ready(2, |_| None).is_empty());
+        queue.finish("chat");
+        assert_eq!(ids(&queue.take_ready(2, |_| None)), vec!["r2"]);
+    }
+
+    #[test]
+    fn test_folder_limit_does_not_block_other_folders() {
+        let mut queue = RunQueue::new();
+        queue.push("a1".into(), "/a".into(), RunPriority::Ralph, ());
+        queue.push("a2".into(), "/a".into(), RunPriority::Ralph, ());
+        queue.push("b1".into(), "/b".into(), RunPriority::Ralph, ());
+
+        let limit = |folder: &str| (folder == "/a").then_some(1);
+        assert_eq!(ids(&queue.take_ready(10, limit)), vec!["a1", "b1"]);
+        let pending: Vec<&str> = queue.pending().map(|q| q.id.as_str()).collect();
+        assert_eq!(pending, vec!["a2"]);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut queue = RunQueue::new();
+        queue.push("a".into(), "/a".into(), RunPriority::Ralph, ());
+        queue.push("b".into(), "/a".into(), RunPriority::Ralph, ());
+
+        assert!(queue.cancel("a").is_some());
+        assert!(queue.cancel("a").is_none());
+        assert_eq!(ids(&queue.cancel_all()), vec!["b"]);
+        assert!(queue.take_ready(1, |_| None).is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+use super::env_file::EnvVars;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+#[cfg(unix)]
+use std::time::Duration;
+
+/// How long the login shell may take to print its environment
+#[cfg(unix)]
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Brackets the `env` output so shell startup noise can be ignored
+#[cfg(unix)]
+const MARKER: &str = "__TRELLICO_SHELL_ENV__";
+
+/// Variables that describe the capturing shell rather than the user's setup
+#[cfg(unix)]
+const SHELL_LOCAL_VARS: &[&str] = &["PWD", "OLDPWD", "SHLVL", "_"];
+
+static LOGIN_ENV: OnceLock<EnvVars> = OnceLock::new();
+
+/// The environment a terminal would give the user: this process's environment
+/// overlaid with what their login shell exports. GUI launches on macOS start
+/// with a minimal PATH, so agents would otherwise miss tools like `cargo`,
+/// `pnpm` or nvm's `node`. Captured once, then cached.
+pub fn login_env() -> &'static EnvVars {
+    LOGIN_ENV.get_or_init(|| {
+        let mut env: EnvVars = std::env::vars().collect();
+        #[cfg(unix)]
+        match capture_login_env(&login_shell(), CAPTURE_TIMEOUT) {
+            Ok(captured) => env.extend(captured),
+            Err(e) => eprintln!("Failed to capture login shell environment: {}", e),
+        }
+        env
+    })
+}
+
+/// Find an executable on the login shell's PATH
+pub fn find_in_path(binary_name: &str) -> Option<PathBuf> {
+    let path = login_env().get("PATH")?;
+    std::env::split_paths(path)
+        .map(|dir| dir.join(binary_name))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(unix)]
+fn login_shell() -> String {
+    std::env::var("SHELL")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "/bin/sh".to_string())
+}
+
+/// Run `shell` as an interactive login shell and read the environment it ends
+/// up with. Interactive, because version managers are often set up in rc files
+/// that only interactive shells read.
+#[cfg(unix)]
+fn capture_login_env(shell: &str, timeout: Duration) -> Result<EnvVars, String> {
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+    use std::sync::mpsc;
+
+    let child = Command::new(shell)
+        .args(["-i", "-l", "-c"])
+        .arg(format!("echo {0}; env; echo {0}", MARKER))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        // Its own process group, so an interactive shell can't grab our terminal
+        // and a timeout can kill whatever its rc files started
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", shell, e))?;
+    let pid = child.id() as libc::pid_t;
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(child.wait_with_output());
+    });
+
+    match rx.recv_timeout(timeout) {
+        Ok(Ok(output)) => parse_env_output(&String::from_utf8_lossy(&output.stdout))
+            .ok_or_else(|| format!("{} did not print its environment", shell)),
+        Ok(Err(e)) => Err(format!("Failed to run {}: {}", shell, e)),
+        Err(_) => {
+            // SAFETY: kill only sends a signal; the group is led by our unreaped child
+            unsafe {
+                libc::kill(-pid, libc::SIGKILL);
+            }
+            Err(format!("{} timed out after {:?}", shell, timeout))
+        }
+    }
+}
+
+/// Parse `env` output between the marker lines (the marker also shows up
+/// inside `$_`). Lines that don't start a new `KEY=` belong to the previous
+/// value, which spans lines.
+#[cfg(unix)]
+fn parse_env_output(output: &str) -> Option<EnvVars> {
+    let mut lines = output.lines();
+    lines.find(|line| *line == MARKER)?;
+    let mut body = Vec::new();
+    loop {
+        // Without the closing marker the output was cut short
+        let line = lines.next()?;
+        if line == MARKER {
+            break;
+        }
+        body.push(line);
+    }
+
+    let mut env = EnvVars::new();
+    let mut current: Option<String> = None;
+    for line in body {
+        let assignment = line
+            .split_once('=')
+            .filter(|(key, _)| super::env_file::is_valid_key(key));
+        match (assignment, &current) {
+            (Some((key, value)), _) => {
+                env.insert(key.to_string(), value.to_string());
+                current = Some(key.to_string());
+            }
+            (None, Some(key)) => {
+                if let Some(value) = env.get_mut(key) {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    for key in SHELL_LOCAL_VARS {
+        env.remove(*key);
+    }
+    Some(env)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_output_skips_noise() {
+        let output = format!(
+            "Welcome!\n{0}\nPATH=/usr/bin:/opt/bin\nMULTI=a\nb\nSHLVL=2\n_={0}\nLAST=1\n{0}\nbye\n",
+            MARKER
+        );
+        let env = parse_env_output(&output).unwrap();
+        assert_eq!(env["PATH"], "/usr/bin:/opt/bin");
+        assert_eq!(env["MULTI"], "a\nb");
+        assert!(!env.contains_key("SHLVL"));
+        assert!(!env.contains_key("Welcome!"));
+        assert_eq!(env["LAST"], "1");
+
+        assert!(parse_env_output("no markers").is_none());
+    }
+
+    #[test]
+    fn test_capture_login_env_from_sh() {
+        let env = capture_login_env("/bin/sh", Duration::from_secs(10)).unwrap();
+        assert!(env.contains_key("PATH"));
+    }
+
+    #[test]
+    fn test_capture_login_env_times_out() {
+        let script =
+            std::env::temp_dir().join(format!("trellico-slow-shell-{}.sh", uuid::Uuid::new_v4()));
+        std::fs::write(&script, "#!/bin/sh\nsleep 10\n").unwrap();
+        std::fs::set_permissions(&script, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+            .unwrap();
+
+        let started = std::time::Instant::now();
+        let result = capture_login_env(script.to_str().unwrap(), Duration::from_millis(200));
+        assert!(result.unwrap_err().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let _ = std::fs::remove_file(&script);
+    }
+}
@@ -0,0 +1,169 @@
+//! End-to-end tests for the run pipeline: the replay provider is spawned through
+//! `commands::provider` like a real agent, its output is parsed and recorded
+//! into the database, and the events a view would receive are captured.
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once};
+use tauri::Listener;
+use trellico_lib::test_support::{self, Provider};
+
+/// Keep the database and run logs in a scratch home, and play fixtures with
+/// the app binary, once per test binary. The database stays open for every
+/// test, so the home is cleared when the tests start rather than after each.
+fn setup() {
+    static SETUP: Once = Once::new();
+    SETUP.call_once(|| {
+        let home = Path::new(env!("CARGO_TARGET_TMPDIR")).join("replay-home");
+        let _ = std::fs::remove_dir_all(&home);
+        std::fs::create_dir_all(&home).unwrap();
+        test_support::init(&home, Path::new(env!("CARGO_BIN_EXE_trellico"))).unwrap();
+    });
+}
+
+/// A project folder, removed when the test that made it ends
+struct TempProject(PathBuf);
+
+impl Drop for TempProject {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+/// Create a folder with a two-story PRD named "feature" that replays `fixture_name`
+fn temp_project(fixture_name: &str) -> TempProject {
+    let dir = std::env::temp_dir().join(format!("trellico-e2e-{}", uuid::Uuid::new_v4()));
+    let project = TempProject(dir.clone());
+    let prd_dir = dir.join(".trellico").join("ralph").join("feature");
+    std::fs::create_dir_all(&prd_dir).unwrap();
+    std::fs::write(
+        prd_dir.join("prd.json"),
+        r#"{"userStories":[{"id":"US-001","priority":1,"passes":false},{"id":"US-002","priority":2,"passes":false}]}"#,
+    )
+    .unwrap();
+    std::fs::copy(
+        fixture(fixture_name),
+        dir.join(".trellico").join("replay.json"),
+    )
+    .unwrap();
+    project
+}
+
+/// The exit code of one run, and the events it emitted as (name, payload)
+fn run_replay(folder: &Path, session_id: Option<&str>) -> (i32, Vec<(String, Value)>) {
+    setup();
+    let app = tauri::test::mock_app();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    for name in ["ai-session", "ai-event", "ai-output", "process-started"] {
+        let events = events.clone();
+        app.handle().listen_any(name, move |event| {
+            let payload: Value = serde_json::from_str(event.payload()).unwrap();
+            events.lock().unwrap().push((name.to_string(), payload));
+        });
+    }
+
+    let (process_id, code) = test_support::run_single_attempt(
+        app.handle(),
+        Provider::from_id("replay").unwrap(),
+        "go".to_string(),
+        folder.to_string_lossy().into_owned(),
+        session_id.map(String::from),
+    )
+    .unwrap();
+
+    let events = events
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, payload)| payload["process_id"] == process_id.as_str())
+        .cloned()
+        .collect();
+    (code, events)
+}
+
+/// Text of every `text` stream event
+fn texts(events: &[(String, Value)]) -> Vec<String> {
+    events
+        .iter()
+        .filter(|(name, payload)| name == "ai-event" && payload["event"]["kind"] == "text")
+        .map(|(_, payload)| payload["event"]["text"].as_str().unwrap().to_string())
+        .collect()
+}
+
+fn prd_passes(folder: &Path) -> Vec<bool> {
+    let path = folder.join(".trellico/ralph/feature/prd.json");
+    let prd: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    prd["userStories"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["passes"].as_bool().unwrap())
+        .collect()
+}
+
+#[test]
+fn replay_ralph_loop_completes_stories() {
+    let project = temp_project("ralph_iteration.json");
+    let folder = &project.0;
+    let session_id = format!("session-{}", uuid::Uuid::new_v4());
+
+    let (code, events) = run_replay(folder, Some(&session_id));
+    assert_eq!(code, 0);
+    assert!(events.iter().any(
+        |(name, payload)| name == "ai-session" && payload["session_id"] == session_id.as_str()
+    ));
+    assert_eq!(texts(&events), vec!["Implementing the next story ✅"]);
+    // Announced with the pid of the spawned provider
+    assert!(events
+        .iter()
+        .any(|(name, payload)| name == "process-started" && payload["pid"].is_u64()));
+    assert_eq!(prd_passes(folder), vec![true, false]);
+
+    // The prompt, then everything the provider said, in order
+    let messages = test_support::session_messages(&session_id).unwrap();
+    let types: Vec<&str> = messages
+        .iter()
+        .map(|m| m["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(types, vec!["user", "system", "assistant", "result"]);
+    assert_eq!(messages[0]["content"], "go");
+
+    // Resuming the session appends to it
+    let (code, events) = run_replay(folder, Some(&session_id));
+    assert_eq!(code, 0);
+    assert!(texts(&events).contains(&"<promise>COMPLETE</promise>".to_string()));
+    assert_eq!(prd_passes(folder), vec![true, true]);
+
+    let messages = test_support::session_messages(&session_id).unwrap();
+    assert_eq!(messages.len(), 9);
+    assert_eq!(
+        messages[7]["message"]["content"][0]["text"],
+        "<promise>COMPLETE</promise>"
+    );
+}
+
+#[test]
+fn replay_injected_failure_sets_exit_code() {
+    let project = temp_project("crash.json");
+    let folder = &project.0;
+
+    let (code, events) = run_replay(folder, None);
+    assert_eq!(code, 42);
+    assert!(events
+        .iter()
+        .any(|(name, payload)| name == "ai-event" && payload["event"]["kind"] == "init"));
+    assert!(events.iter().any(|(name, payload)| {
+        name == "ai-output"
+            && payload["data"]
+                .as_str()
+                .unwrap()
+                .contains("Simulated crash")
+    }));
+}
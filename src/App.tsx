@@ -10,8 +10,15 @@ import {
   useRalphIterations,
   useGeneratingItems,
   useSessionManagement,
+  useProviderNames,
 } from "@/hooks";
-import type { AIMessage, Provider } from "@/types";
+import type {
+  AIMessage,
+  ExitReason,
+  Provider,
+  ProviderSelection,
+  RalphIterationRef,
+} from "@/types";
 import { useMessageStore, useFolderContext } from "@/contexts";
 import { loadSessionToView } from "@/lib/sessionLoader";
 import { Welcome } from "@/components/Welcome";
@@ -97,6 +104,7 @@ function App() {
 
   // Provider from folder context
   const provider = activeFolder?.provider ?? "claude_code";
+  const providerName = useProviderNames();
 
   // Generating items hook
   const {
@@ -115,17 +123,17 @@ function App() {
   });
 
   // Store ralph iterations handlers in refs that can be updated
-  const handleAIExitRef = React.useRef<((messages: AIMessage[], sessionId: string) => void) | null>(
-    null
-  );
+  const handleAIExitRef = React.useRef<
+    ((messages: AIMessage[], sessionId: string, reason: ExitReason) => void) | null
+  >(null);
   const handleAIErrorRef = React.useRef<((processId: string) => void) | null>(null);
   const handleSessionIdReceivedRef = React.useRef<
     ((processId: string, sessionId: string) => void) | null
   >(null);
 
   // AI session hook - callbacks use refs to get latest handlers
-  const aiExitCallback = useCallback((msgs: AIMessage[], sessionId: string) => {
-    handleAIExitRef.current?.(msgs, sessionId);
+  const aiExitCallback = useCallback((msgs: AIMessage[], sessionId: string, reason: ExitReason) => {
+    handleAIExitRef.current?.(msgs, sessionId, reason);
   }, []);
   const aiErrorCallback = useCallback((processId: string) => {
     handleAIErrorRef.current?.(processId);
@@ -163,10 +171,44 @@ function App() {
 
   // Ralph iterations hook
   const runAIForRalph = useCallback(
-    (message: string, folderPath: string, sessionId: string | null) => {
-      return runAI(message, folderPath, sessionId, provider);
+    async (
+      message: string,
+      folderPath: string,
+      sessionId: string | null,
+      ralphIteration?: RalphIterationRef
+    ) => {
+      // Continue on the folder's next healthy fallback if this provider is
+      // logged out or rate-limited
+      const selection = ralphIteration
+        ? await invoke<ProviderSelection>("select_ralph_provider", {
+            folderPath,
+            prdName: ralphIteration.prd_name,
+            provider,
+          })
+        : { provider, fallback_reason: null };
+
+      const processId = await runAI(
+        message,
+        folderPath,
+        sessionId,
+        selection.provider,
+        undefined,
+        "plan",
+        undefined,
+        ralphIteration
+      );
+      if (selection.provider !== provider) {
+        store.addMessage(
+          {
+            type: "system",
+            content: `Running on ${providerName(selection.provider)} instead of ${providerName(provider)}: ${selection.fallback_reason}`,
+          },
+          processId
+        );
+      }
+      return processId;
     },
-    [runAI, provider]
+    [runAI, provider, providerName, store]
   );
 
   const ralphIterations = useRalphIterations({
@@ -418,8 +460,7 @@ function App() {
       store.state.activeSessionProvider &&
       store.state.activeSessionProvider !== provider
     ) {
-      const sessionProvider =
-        store.state.activeSessionProvider === "claude_code" ? "Claude Code" : "Amp";
+      const sessionProvider = providerName(store.state.activeSessionProvider);
       setProviderMismatchError(
         `This session was started with ${sessionProvider}. Please switch to ${sessionProvider} to continue.`
       );
@@ -726,7 +767,11 @@ function App() {
             ? "Provider Not Installed"
             : aiError?.type === "not_logged_in"
               ? "Provider Not Logged In"
-              : "Provider Error"
+              : aiError?.type === "unsupported_version"
+                ? "Provider Update Required"
+                : aiError?.type === "budget_exceeded"
+                  ? "Budget Exceeded"
+                  : "Provider Error"
         }
         message={
           aiError?.authInstructions
@@ -1,3 +1,6 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Terminal } from "lucide-react";
 import {
   Select,
   SelectContent,
@@ -5,7 +8,7 @@ import {
   SelectTrigger,
   SelectValue,
 } from "@/components/ui/select";
-import type { Provider } from "@/types";
+import type { Provider, ProviderInfo } from "@/types";
 
 interface ProviderSelectorProps {
   value: Provider;
@@ -13,37 +16,59 @@ interface ProviderSelectorProps {
   disabled?: boolean;
 }
 
-const PROVIDERS: { value: Provider; label: string; icon: string }[] = [
+interface ProviderOption {
+  value: Provider;
+  label: string;
+  icon?: string;
+}
+
+const BUILTIN_PROVIDERS: ProviderOption[] = [
   { value: "claude_code", label: "Claude Code", icon: "/logos/claude-icon.svg" },
   { value: "amp", label: "Amp", icon: "/logos/amp-icon.svg" },
+  { value: "codex", label: "Codex", icon: "/logos/codex-icon.svg" },
 ];
 
+function ProviderIcon({ option }: { option: ProviderOption }) {
+  if (!option.icon) {
+    return <Terminal className="w-4 h-4 shrink-0 text-muted-foreground" />;
+  }
+  return <img src={option.icon} alt={option.label} className="w-4 h-4 rounded shrink-0" />;
+}
+
 export function ProviderSelector({ value, onChange, disabled }: ProviderSelectorProps) {
-  const selectedProvider = PROVIDERS.find((p) => p.value === value) || PROVIDERS[0];
+  const [providers, setProviders] = useState<ProviderOption[]>(BUILTIN_PROVIDERS);
+
+  // Append user-defined providers registered in the backend
+  useEffect(() => {
+    invoke<ProviderInfo[]>("list_providers")
+      .then((registered) => {
+        const custom = registered
+          .filter((p) => !BUILTIN_PROVIDERS.some((b) => b.value === p.id))
+          .map((p) => ({ value: p.id, label: p.display_name }));
+        setProviders([...BUILTIN_PROVIDERS, ...custom]);
+      })
+      .catch(() => {
+        // Fall back to built-in providers
+      });
+  }, []);
+
+  const selectedProvider = providers.find((p) => p.value === value) || providers[0];
 
   return (
     <Select value={value} onValueChange={(v) => onChange(v as Provider)} disabled={disabled}>
       <SelectTrigger className="h-8 w-full text-xs">
         <SelectValue>
           <div className="flex items-center gap-2">
-            <img
-              src={selectedProvider.icon}
-              alt={selectedProvider.label}
-              className="w-4 h-4 rounded shrink-0"
-            />
+            <ProviderIcon option={selectedProvider} />
             <span>{selectedProvider.label}</span>
           </div>
         </SelectValue>
       </SelectTrigger>
       <SelectContent>
-        {PROVIDERS.map((provider) => (
+        {providers.map((provider) => (
           <SelectItem key={provider.value} value={provider.value}>
             <div className="flex items-center gap-2">
-              <img
-                src={provider.icon}
-                alt={provider.label}
-                className="w-4 h-4 rounded shrink-0"
-              />
+              <ProviderIcon option={provider} />
               <span>{provider.label}</span>
             </div>
           </SelectItem>
@@ -102,7 +102,9 @@ function StatusDot({ status }: { status: string }) {
         "w-1.5 h-1.5 rounded-full shrink-0",
         status === "running" && "bg-yellow-500 animate-pulse",
         status === "completed" && "bg-green-500",
-        status === "stopped" && "bg-red-500"
+        status === "stopped" && "bg-red-500",
+        status === "timed_out" && "bg-orange-500",
+        status === "failed" && "bg-red-700"
       )}
     />
   );
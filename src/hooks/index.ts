@@ -7,3 +7,4 @@ export { useRalphIterations } from "./useRalphIterations";
 export { useUpdater } from "./useUpdater";
 export { useGeneratingItems } from "./useGeneratingItems";
 export { useSessionManagement } from "./useSessionManagement";
+export { useProviderNames } from "./useProviderNames";
@@ -1,17 +1,28 @@
 import { useEffect, useRef, useCallback, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
-import type { AIMessage, Provider } from "@/types";
+import type {
+  AIMessage,
+  BudgetExceeded,
+  AIRetry,
+  AISession,
+  AITimeout,
+  ExitReason,
+  Provider,
+  RalphIterationRef,
+} from "@/types";
 import { useMessageStore } from "@/contexts";
 
 interface AIOutput {
   process_id: string;
+  offset: number;
   data: string;
 }
 
 interface AIExit {
   process_id: string;
   code: number;
+  reason: ExitReason;
 }
 
 interface AIError {
@@ -22,27 +33,26 @@ interface AIError {
 interface ProcessInfo {
   sessionId: string;
   buffer: string;
-  provider: string;
-  sessionType: "plan" | "ralph_prd";
   onExit?: (messages: AIMessage[]) => void;
-  initialUserMessage?: string; // Track user message for new sessions (saved to DB when session ID arrives)
 }
 
 interface ProviderStatus {
   available: boolean;
   error: string | null;
-  error_type: string | null; // "not_installed", "not_logged_in", "unknown"
+  error_type: string | null; // "not_installed", "not_logged_in", "unsupported_version", "unknown"
   auth_instructions: string | null; // Instructions for authenticating
+  version: string | null;
+  binary_path: string | null;
 }
 
 export interface ProviderAvailabilityError {
   message: string;
-  type: "not_installed" | "not_logged_in" | "unknown";
+  type: "not_installed" | "not_logged_in" | "unsupported_version" | "budget_exceeded" | "unknown";
   authInstructions?: string;
 }
 
 interface UseAISessionOptions {
-  onAIExit?: (messages: AIMessage[], sessionId: string) => void;
+  onAIExit?: (messages: AIMessage[], sessionId: string, reason: ExitReason) => void;
   onAIError?: (processId: string) => void;
   onSessionIdReceived?: (processId: string, sessionId: string) => void;
   folderPath?: string | null;
@@ -64,9 +74,6 @@ export function useAISession(options: UseAISessionOptions = {}) {
     folderPathRef.current = folderPath;
   }, [folderPath]);
 
-  // Track message sequence numbers per session for DB persistence
-  const sequenceCounters = useRef<Map<string, number>>(new Map());
-
   // Track provider availability errors
   const [aiError, setAIError] = useState<ProviderAvailabilityError | null>(null);
 
@@ -156,78 +163,36 @@ export function useAISession(options: UseAISessionOptions = {}) {
               return;
             }
 
-            // Extract session ID from init message
-            if (parsed.type === "system" && parsed.subtype === "init" && parsed.session_id) {
-              // Update session ID if we got it from init (for new sessions)
-              if (processInfo.sessionId !== parsed.session_id) {
-                processInfo.sessionId = parsed.session_id;
-                store.setLiveSessionId(parsed.session_id, process_id);
-                // Initialize sequence counter for this session
-                // If there's an initial user message, start at 1 (user message gets sequence 1)
-                const startSequence = processInfo.initialUserMessage ? 1 : 0;
-                sequenceCounters.current.set(parsed.session_id, startSequence);
-
-                // Create session in database immediately
-                const folderPath = store.getProcessFolderPath(process_id);
-                if (folderPath) {
-                  invoke("db_create_session", {
-                    sessionId: parsed.session_id,
-                    folderPath,
-                    provider: processInfo.provider,
-                    sessionType: processInfo.sessionType,
-                  }).then(() => {
-                    // Save the initial user message to DB (for new sessions)
-                    if (processInfo.initialUserMessage) {
-                      invoke("db_save_message", {
-                        sessionId: parsed.session_id,
-                        messageJson: JSON.stringify({ type: "user", content: processInfo.initialUserMessage }),
-                        sequence: 1,
-                        messageType: "user",
-                      }).catch(() => {
-                        // Failed to save initial user message
-                      });
-                    }
-                  }).catch(() => {
-                    // Failed to create session in DB
-                  });
-                }
-
-                // Notify listeners so they can persist the session ID immediately
-                onSessionIdReceivedRef.current?.(process_id, parsed.session_id);
-              }
-            }
-
             // Skip user messages from the stream - we already add them ourselves in runAI()
             if (parsed.type === "user" && !parsed.parent_tool_use_id) {
               continue;
             }
 
+            // The backend persists messages as they stream
             store.addMessage(parsed, process_id);
-
-            // Persist message to database if we have a valid session ID
-            const sessionId = processInfo.sessionId;
-            if (sessionId && !sessionId.startsWith("__pending__")) {
-              const seq = (sequenceCounters.current.get(sessionId) ?? 0) + 1;
-              sequenceCounters.current.set(sessionId, seq);
-              invoke("db_save_message", {
-                sessionId,
-                messageJson: JSON.stringify(parsed),
-                sequence: seq,
-                messageType: parsed.type,
-              }).catch((err) => {
-                console.error("Failed to save message to DB:", err);
-              });
-            }
           } catch {
             // Not valid JSON, ignore
           }
         }
       });
 
+      // The backend writes the sessions row (and the user message) before emitting this
+      const sessionUnlisten = await listen<AISession>("ai-session", (event) => {
+        if (!mounted) return;
+
+        const { process_id, session_id } = event.payload;
+        const processInfo = processesRef.current.get(process_id);
+        if (!processInfo || processInfo.sessionId === session_id) return;
+
+        processInfo.sessionId = session_id;
+        store.setLiveSessionId(session_id, process_id);
+        onSessionIdReceivedRef.current?.(process_id, session_id);
+      });
+
       const exitUnlisten = await listen<AIExit>("ai-exit", (event) => {
         if (!mounted) return;
 
-        const { process_id } = event.payload;
+        const { process_id, reason } = event.payload;
         const processInfo = processesRef.current.get(process_id);
         if (!processInfo) return;
 
@@ -237,13 +202,54 @@ export function useAISession(options: UseAISessionOptions = {}) {
         if (processInfo.onExit) {
           processInfo.onExit(messages);
         } else if (onAIExitRef.current) {
-          onAIExitRef.current(messages, processInfo.sessionId);
+          onAIExitRef.current(messages, processInfo.sessionId, reason);
         }
 
         store.endProcess(process_id);
         processesRef.current.delete(process_id);
       });
 
+      // The watchdog is terminating the run; ai-exit follows with reason "timed_out"
+      const timeoutUnlisten = await listen<AITimeout>("ai-timeout", (event) => {
+        if (!mounted) return;
+
+        const { process_id, kind, limit_secs } = event.payload;
+        if (!processesRef.current.has(process_id)) return;
+
+        const limit = kind === "idle" ? `no output for ${limit_secs}s` : `ran longer than ${limit_secs}s`;
+        store.addMessage({ type: "system", content: `Timed out: ${limit}` }, process_id);
+      });
+
+      // A rate limit or overload; the run resumes by itself after the delay
+      const retryUnlisten = await listen<AIRetry>("ai-retry", (event) => {
+        if (!mounted) return;
+
+        const { process_id, attempt, max_retries, delay_secs, error } = event.payload;
+        if (!processesRef.current.has(process_id)) return;
+
+        store.addMessage(
+          {
+            type: "system",
+            content: `Temporary provider error, retrying in ${delay_secs}s (retry ${attempt - 1} of ${max_retries}): ${error}`,
+          },
+          process_id
+        );
+      });
+
+      // A Ralph iteration was refused because a folder budget is spent
+      const budgetUnlisten = await listen<BudgetExceeded>("budget-exceeded", (event) => {
+        if (!mounted) return;
+
+        const { prd_name, budget, limit, used } = event.payload;
+        const detail =
+          budget === "daily_cost"
+            ? `$${used.toFixed(2)} of the $${limit.toFixed(2)} daily budget has been spent.`
+            : budget === "prd_tokens"
+              ? `${prd_name} has used ${used.toLocaleString()} of its ${limit.toLocaleString()} token budget.`
+              : `This run has reached its limit of ${limit} iterations.`;
+        setAIError({ message: `Ralph stopped: ${detail}`, type: "budget_exceeded" });
+      });
+
       const errorUnlisten = await listen<AIError>("ai-error", (event) => {
         if (!mounted) return;
 
@@ -270,7 +276,15 @@ export function useAISession(options: UseAISessionOptions = {}) {
         }
       });
 
-      unlisteners = [outputUnlisten, exitUnlisten, errorUnlisten];
+      unlisteners = [
+        outputUnlisten,
+        sessionUnlisten,
+        exitUnlisten,
+        timeoutUnlisten,
+        retryUnlisten,
+        budgetUnlisten,
+        errorUnlisten,
+      ];
     };
 
     setupListeners();
@@ -289,7 +303,8 @@ export function useAISession(options: UseAISessionOptions = {}) {
       provider: Provider,
       userMessageToShow?: string,
       sessionType: "plan" | "ralph_prd" = "plan",
-      onExit?: (messages: AIMessage[]) => void
+      onExit?: (messages: AIMessage[]) => void,
+      ralphIteration?: RalphIterationRef
     ): Promise<string> => {
       // Check provider availability before starting
       const status = await invoke<ProviderStatus>("check_provider_available", { provider });
@@ -308,17 +323,18 @@ export function useAISession(options: UseAISessionOptions = {}) {
         message,
         folderPath,
         sessionId,
+        options: {
+          session_type: sessionType,
+          ralph_iteration: ralphIteration ?? null,
+          user_message: userMessageToShow ?? null,
+        },
       });
 
       // Track this process
-      // For new sessions (no sessionId), store the user message to persist to DB when session ID arrives
       processesRef.current.set(processId, {
         sessionId: sessionId || "__pending__",
         buffer: "",
-        provider,
-        sessionType,
         onExit,
-        initialUserMessage: !sessionId ? userMessageToShow : undefined,
       });
 
       // Start live session in store
@@ -327,20 +343,6 @@ export function useAISession(options: UseAISessionOptions = {}) {
       // Add the user message to display
       if (userMessageToShow) {
         store.addMessage({ type: "user", content: userMessageToShow }, processId);
-
-        // If we have an existing session ID (resuming), save the user message to DB
-        if (sessionId) {
-          const seq = (sequenceCounters.current.get(sessionId) ?? 0) + 1;
-          sequenceCounters.current.set(sessionId, seq);
-          invoke("db_save_message", {
-            sessionId,
-            messageJson: JSON.stringify({ type: "user", content: userMessageToShow }),
-            sequence: seq,
-            messageType: "user",
-          }).catch((err) => {
-            console.error("Failed to save user message to DB:", err);
-          });
-        }
       }
 
       return processId;
@@ -1,7 +1,7 @@
 import { useState, useEffect, useRef, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
-import type { AIMessage, FolderSession } from "@/types";
+import type { AIMessage, FolderSession, Provider } from "@/types";
 import { useMessageStore } from "@/contexts";
 
 interface DbSessionLink {
@@ -117,7 +117,7 @@ export function usePlans({ folderPath, onPlanLinked }: UsePlansOptions) {
 
               // Abort if folder changed during async operation
               if (folderPathRef.current !== capturedFolderPath) return;
-              store.viewSession(link.session_id, history, link.provider as "claude_code" | "amp");
+              store.viewSession(link.session_id, history, link.provider as Provider);
             } catch {
               store.viewSession(null);
             }
@@ -331,7 +331,7 @@ export function usePlans({ folderPath, onPlanLinked }: UsePlansOptions) {
           sessionId: session.id,
         });
         setLinkedSessionId(session.id);
-        store.viewSession(session.id, history, session.provider as "claude_code" | "amp");
+        store.viewSession(session.id, history, session.provider as Provider);
       } else {
         setLinkedSessionId(null);
         viewSession(null);
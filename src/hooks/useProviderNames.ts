@@ -0,0 +1,20 @@
+import { useCallback, useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import type { Provider, ProviderInfo } from "@/types";
+
+// Display names of the registered providers; unknown ids are shown as-is
+export function useProviderNames() {
+  const [names, setNames] = useState<Record<string, string>>({});
+
+  useEffect(() => {
+    invoke<ProviderInfo[]>("list_providers")
+      .then((providers) =>
+        setNames(Object.fromEntries(providers.map((p) => [p.id, p.display_name])))
+      )
+      .catch(() => {
+        // Fall back to showing ids
+      });
+  }, []);
+
+  return useCallback((provider: Provider) => names[provider] ?? provider, [names]);
+}
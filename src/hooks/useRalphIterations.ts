@@ -1,12 +1,17 @@
 import { useState, useCallback, useRef, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import type { AIMessage, RalphIteration } from "@/types";
+import type { AIMessage, ExitReason, RalphIteration, RalphIterationRef } from "@/types";
 import { getRalphPrompt } from "@/prompts";
 import { useMessageStore } from "@/contexts";
 
 interface UseRalphIterationsProps {
   folderPath: string | null;
-  runAI: (message: string, folderPath: string, sessionId: string | null) => Promise<string>;
+  runAI: (
+    message: string,
+    folderPath: string,
+    sessionId: string | null,
+    ralphIteration?: RalphIterationRef
+  ) => Promise<string>;
   onAutoSelectIteration?: () => void;
 }
 
@@ -22,7 +27,7 @@ interface UseRalphIterationsReturn {
   selectIteration: (prdName: string, iterationNumber: number) => void;
   clearIterationSelection: () => void;
   clearIterationsForPrd: (prdName: string) => void;
-  handleAIExit: (messages: AIMessage[], sessionId: string) => void;
+  handleAIExit: (messages: AIMessage[], sessionId: string, reason: ExitReason) => void;
   handleAIError: (processId: string) => void;
   handleSessionIdReceived: (processId: string, sessionId: string) => void;
 }
@@ -185,7 +190,10 @@ export function useRalphIterations({
       const prdPath = `.trellico/ralph/${prdName}/prd.json`;
       const prompt = getRalphPrompt(prdPath);
 
-      const processId = await runAI(prompt, folderPath, null);
+      const processId = await runAI(prompt, folderPath, null, {
+        prd_name: prdName,
+        iteration_number: iterationNumber,
+      });
       return processId;
     },
     [folderPath, runAI]
@@ -324,39 +332,27 @@ export function useRalphIterations({
     });
   }, []);
 
-  // Handle session ID received - persist immediately so we don't lose it on crash
+  // Handle session ID received - the backend has already linked it to the iteration
   const handleSessionIdReceived = useCallback(
-    async (processId: string, sessionId: string) => {
+    (processId: string, sessionId: string) => {
       const currentState = ralphStateRef.current;
-      // Only persist if this is the process we're tracking
+      // Only update if this is the process we're tracking
       if (currentState.status !== "running" || currentState.processId !== processId) return;
-      if (!folderPath) return;
 
       const { prdName, iterationNumber } = currentState;
-      try {
-        await invoke("db_update_ralph_iteration_session_id", {
-          folderPath,
-          prdName,
-          iterationNumber,
-          sessionId,
-        });
-        // Update local state
-        setIterations((prev) => ({
-          ...prev,
-          [prdName]: (prev[prdName] || []).map((i): RalphIteration =>
-            i.iteration_number === iterationNumber ? { ...i, session_id: sessionId } : i
-          ),
-        }));
-      } catch (err) {
-        console.error("Failed to persist session ID:", err);
-      }
+      setIterations((prev) => ({
+        ...prev,
+        [prdName]: (prev[prdName] || []).map((i): RalphIteration =>
+          i.iteration_number === iterationNumber ? { ...i, session_id: sessionId } : i
+        ),
+      }));
     },
-    [folderPath]
+    []
   );
 
   // Handle AI exit - called from useAISession when ai-exit event fires
   const handleAIExit = useCallback(
-    async (messages: AIMessage[], sessionId: string) => {
+    async (messages: AIMessage[], sessionId: string, reason: ExitReason) => {
       // Read current state from ref (always up-to-date)
       const currentState = ralphStateRef.current;
       if (currentState.status !== "running") return;
@@ -370,6 +366,28 @@ export function useRalphIterations({
 
       if (!folderPath) return;
 
+      // Taken out of the run queue before it started; the loop ends here
+      if (reason === "cancelled") {
+        setRalphState({ status: "idle" });
+        await markIterationStopped(prdName, iterationNumber);
+        return;
+      }
+
+      // Retries of a transient provider failure ran out; the backend already
+      // marked the iteration failed, and the loop stops rather than burn more runs
+      if (reason === "failed") {
+        setIterations((prev) => ({
+          ...prev,
+          [prdName]: (prev[prdName] || []).map((i) =>
+            i.iteration_number === iterationNumber
+              ? { ...i, status: "failed" as const, session_id: sessionId }
+              : i
+          ),
+        }));
+        setRalphState({ status: "idle" });
+        return;
+      }
+
       try {
         // Persist the session ID to the database
         if (sessionId) {
@@ -381,8 +399,8 @@ export function useRalphIterations({
           });
         }
 
-        // Check if complete
-        if (isComplete(messages)) {
+        // Check if complete (a timed out run never is)
+        if (reason !== "timed_out" && isComplete(messages)) {
           // Mark iteration as completed and stop ralphing
           await invoke("db_update_ralph_iteration_status", {
             folderPath,
@@ -404,12 +422,14 @@ export function useRalphIterations({
           // Update state machine to idle
           setRalphState({ status: "idle" });
         } else {
-          // Mark current iteration as completed (it finished without COMPLETE signal)
+          // Mark current iteration as done (it finished without COMPLETE signal)
+          // and move on; a hung iteration shouldn't block the loop
+          const finishedStatus = reason === "timed_out" ? "timed_out" : "completed";
           await invoke("db_update_ralph_iteration_status", {
             folderPath,
             prdName,
             iterationNumber,
-            status: "completed",
+            status: finishedStatus,
           });
 
           // Start next iteration
@@ -428,7 +448,7 @@ export function useRalphIterations({
             [prdName]: [
               ...(prev[prdName] || []).map((i) =>
                 i.iteration_number === iterationNumber
-                  ? { ...i, status: "completed" as const, session_id: sessionId }
+                  ? { ...i, status: finishedStatus, session_id: sessionId }
                   : i
               ),
               {
@@ -446,9 +466,12 @@ export function useRalphIterations({
           const prompt = getRalphPrompt(prdPath);
           let processId: string;
           try {
-            processId = await runAI(prompt, folderPath, null);
+            processId = await runAI(prompt, folderPath, null, {
+              prd_name: prdName,
+              iteration_number: nextIterationNumber,
+            });
           } catch (err) {
-            // Failed to start next iteration, mark it as stopped
+            // Failed to start next iteration (e.g. budget exhausted), mark it as stopped
             console.error("Failed to start next iteration:", err);
             await markIterationStopped(prdName, nextIterationNumber);
             setRalphState({ status: "idle" });
@@ -17,11 +17,26 @@ export interface AIMessage {
   parent_tool_use_id?: string;
 }
 
-export type Provider = "claude_code" | "amp";
+// Built-in provider ids, plus any user-defined id from ~/.trellico/providers.toml
+export type Provider = "claude_code" | "amp" | "codex" | (string & {});
+
+// A provider the backend can run (list_providers)
+export interface ProviderInfo {
+  id: Provider;
+  display_name: string;
+}
+
+export type PermissionProfile =
+  | { kind: "full" }
+  | { kind: "no-network-tools" }
+  | { kind: "read-only" }
+  | { kind: "custom"; allowed_tools: string[]; bash_patterns: string[] };
 
 export interface FolderSession {
   id: string;
   provider: string;
+  model: string | null;
+  permission_profile: PermissionProfile | null;
   session_type: "plan" | "ralph_prd";
   display_name: string | null;
   created_at: string;
@@ -39,10 +54,17 @@ export interface SessionPlanLink {
   provider: Provider;
 }
 
+// The provider a Ralph iteration runs on (select_ralph_provider); a fallback
+// when the preferred provider is logged out, rate-limited or unavailable
+export interface ProviderSelection {
+  provider: Provider;
+  fallback_reason: string | null;
+}
+
 export interface RalphIteration {
   iteration_number: number;
   session_id: string;
-  status: "running" | "completed" | "stopped";
+  status: "running" | "completed" | "stopped" | "timed_out" | "failed";
   created_at: string;
   provider: Provider | null;
 }
@@ -55,3 +77,189 @@ export interface GeneratingItem {
   targetName?: string;     // For ralph_prd: the expected prd name (plan filename)
   provider?: Provider;     // Provider used for this session (for enforcing provider match on continue)
 }
+
+export interface TokenUsage {
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_input_tokens: number;
+  cache_read_input_tokens: number;
+}
+
+/** Provider output normalized by the backend, delivered via the `ai-event` event */
+export type StreamEvent =
+  | { kind: "init"; session_id: string; model: string | null }
+  | { kind: "text"; text: string; parent_tool_use_id: string | null }
+  | { kind: "thinking"; text: string }
+  | {
+      kind: "tool_use";
+      id: string | null;
+      name: string;
+      input: unknown;
+      parent_tool_use_id: string | null;
+    }
+  | { kind: "tool_result"; tool_use_id: string | null; content: unknown; is_error: boolean }
+  | { kind: "usage"; usage: TokenUsage; message_id: string | null }
+  | {
+      kind: "result";
+      is_error: boolean;
+      result: string | null;
+      error: string | null;
+      session_id: string | null;
+      duration_ms: number | null;
+      num_turns: number | null;
+      total_cost_usd: number | null;
+      usage: TokenUsage | null;
+    }
+  | { kind: "unknown"; raw: unknown };
+
+// Summed token usage and cost (db_get_*_usage)
+export interface UsageTotals {
+  runs: number;
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_input_tokens: number;
+  cache_read_input_tokens: number;
+  cost_usd: number;
+}
+
+// How a provider's process is attached: a pseudo-terminal, or plain pipes with
+// stdout parsed as NDJSON and stderr reported on its own
+export type RunnerMode = "pty" | "pipe";
+
+// A line a piped provider wrote to stderr
+export interface AIStderr {
+  process_id: string;
+  offset: number;
+  data: string;
+}
+
+// Something a running process produced, with its offset in the process's backlog
+export type ProcessOutput = { offset: number } & (
+  | { type: "output"; data: string }
+  | { type: "event"; event: StreamEvent }
+  | { type: "stderr"; data: string }
+);
+
+// Output a view missed (get_process_output); live events below next_offset are already included
+export interface ProcessOutputSince {
+  entries: ProcessOutput[];
+  next_offset: number;
+  dropped: number;
+}
+
+// Why a provider run ended
+export type ExitReason = "exited" | "stopped" | "timed_out" | "cancelled" | "failed";
+
+// A transient provider failure (rate limit, overload); the run is retried as
+// `attempt` after `delay_secs`, resuming its session
+export interface AIRetry {
+  process_id: string;
+  attempt: number;
+  max_retries: number;
+  delay_secs: number;
+  error: string;
+}
+
+// One attempt of a provider run (db_get_process_attempts, db_get_iteration_attempts)
+export interface RunAttempt {
+  process_id: string;
+  attempt: number;
+  folder_path: string;
+  provider: string;
+  session_id: string | null;
+  ralph_prd: string | null;
+  ralph_iteration: number | null;
+  outcome: "exited" | "stopped" | "timed_out" | "transient_error" | "error";
+  exit_code: number | null;
+  error: string | null;
+  started_at: string;
+  ended_at: string;
+}
+
+// A run's raw transcript log, or its tail (get_run_log)
+export interface RunLogContents {
+  process_id: string;
+  path: string;
+  size: number;
+  offset: number;
+  content: string;
+}
+
+// A run waiting for a concurrency slot; ai-started follows when it runs
+export interface AIQueued {
+  process_id: string;
+  folder_path: string;
+  priority: "interactive" | "ralph";
+  position: number;
+}
+
+export interface AIStarted {
+  process_id: string;
+  queued_ms: number;
+}
+
+// A provider run that has left the queue, in any folder (list_running_processes,
+// process-started once it spawns, process-updated with each retry's pid)
+export interface RunningProcessInfo {
+  process_id: string;
+  provider: Provider;
+  folder_path: string;
+  session_type: "plan" | "ralph_prd";
+  session_id: string | null;
+  model: string | null;
+  permission_profile: PermissionProfile;
+  ralph_iteration: RalphIterationRef | null;
+  plan: string | null;
+  prd: string | null;
+  pid: number | null;
+  started_at: string;
+  bytes_received: number;
+  last_output_at: string | null;
+}
+
+// A running provider process ended; reason and code are null if it couldn't be run
+export interface ProcessEnded {
+  process_id: string;
+  folder_path: string;
+  reason: ExitReason | null;
+  code: number | null;
+}
+
+export interface AITimeout {
+  process_id: string;
+  kind: "max_duration" | "idle";
+  limit_secs: number;
+  elapsed_secs: number;
+}
+
+export interface AISession {
+  process_id: string;
+  session_id: string;
+}
+
+// Identifies the Ralph iteration a provider run belongs to
+export interface RalphIterationRef {
+  prd_name: string;
+  iteration_number: number;
+}
+
+// Per-folder Ralph guardrails; null means no limit
+export interface FolderBudget {
+  max_daily_cost_usd: number | null;
+  max_tokens_per_prd: number | null;
+  max_iterations_per_run: number | null;
+}
+
+export interface BudgetExceeded {
+  folder_path: string;
+  prd_name: string;
+  budget: "daily_cost" | "prd_tokens" | "run_iterations";
+  limit: number;
+  used: number;
+}
+
+export interface AIEvent {
+  process_id: string;
+  offset: number;
+  event: StreamEvent;
+}